@@ -0,0 +1,210 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::error::TaikoError;
+
+/// Folder next to a beatmap file that holds its backups.
+pub const BACKUP_FOLDER: &str = ".taiko-backups";
+
+/// A previous version of a beatmap file, saved as `<stem>.<timestamp>.<extension>` in the
+/// backup folder next to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    /// The file this is a backup of.
+    pub original: PathBuf,
+    pub path: PathBuf,
+    /// UTC time of the backup as `YYYYMMDD-HHMMSS-mmm`, which sorts oldest first.
+    pub stamp: String,
+}
+
+impl Backup {
+    /// The backup time as `YYYY-MM-DD HH:MM:SS UTC`.
+    pub fn time(&self) -> String {
+        let s = &self.stamp;
+        format!(
+            "{}-{}-{} {}:{}:{} UTC",
+            &s[0..4],
+            &s[4..6],
+            &s[6..8],
+            &s[9..11],
+            &s[11..13],
+            &s[13..15]
+        )
+    }
+
+    /// Copies the backup over its original. The current version is backed up first, so a
+    /// restore can be undone.
+    pub fn restore(&self, generations: usize) -> Result<(), TaikoError> {
+        // Read first, as backing up the current version may prune this backup.
+        let data = fs::read(&self.path).map_err(TaikoError::io(&self.path))?;
+        back_up(&self.original, generations.max(1))?;
+        fs::write(&self.original, data).map_err(TaikoError::io(&self.original))
+    }
+}
+
+/// Copies `path` into the backup folder next to it, if it exists, and deletes its oldest
+/// backups beyond `generations`. Nothing is backed up when `generations` is 0.
+pub fn back_up(path: impl AsRef<Path>, generations: usize) -> Result<Option<Backup>, TaikoError> {
+    let path = path.as_ref();
+    if generations == 0 || !path.is_file() {
+        return Ok(None);
+    }
+    let (Some(folder), Some(stem)) = (backup_folder(path), path.file_stem()) else {
+        return Ok(None);
+    };
+
+    fs::create_dir_all(&folder).map_err(TaikoError::io(&folder))?;
+    // Two backups of a file within the same millisecond, e.g. from parallel runs, would get
+    // the same name, so the later one takes the next free millisecond instead.
+    let mut time = SystemTime::now();
+    let (backup, mut file) = loop {
+        let stamp = stamp(time);
+        let mut name = format!("{}.{}", stem.to_string_lossy(), stamp);
+        if let Some(extension) = path.extension() {
+            name = format!("{}.{}", name, extension.to_string_lossy());
+        }
+        let backup = Backup {
+            original: path.to_path_buf(),
+            path: folder.join(name),
+            stamp,
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&backup.path)
+        {
+            Ok(file) => break (backup, file),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                time += Duration::from_millis(1);
+            }
+            Err(e) => return Err(TaikoError::io(&backup.path)(e)),
+        }
+    };
+    File::open(path)
+        .and_then(|mut original| io::copy(&mut original, &mut file))
+        .map_err(TaikoError::io(&backup.path))?;
+
+    for old in backups(path)?.iter().skip(generations) {
+        fs::remove_file(&old.path).map_err(TaikoError::io(&old.path))?;
+    }
+
+    Ok(Some(backup))
+}
+
+/// Like [`back_up`], but does nothing if `path` has already been backed up since `since`.
+/// Watch mode passes the start of its session, so repeated runs keep the version from
+/// before the session instead of rotating it out.
+pub fn back_up_once(
+    path: impl AsRef<Path>,
+    generations: usize,
+    since: SystemTime,
+) -> Result<Option<Backup>, TaikoError> {
+    let path = path.as_ref();
+    let since = stamp(since);
+    if backups(path)?
+        .first()
+        .is_some_and(|newest| newest.stamp >= since)
+    {
+        return Ok(None);
+    }
+    back_up(path, generations)
+}
+
+/// The backups of `path`, newest first.
+pub fn backups(path: impl AsRef<Path>) -> Result<Vec<Backup>, TaikoError> {
+    let path = path.as_ref();
+    let Some(folder) = path.parent() else {
+        return Ok(Vec::new());
+    };
+    let mut found = folder_backups(folder)?;
+    found.retain(|backup| backup.original == path);
+    Ok(found)
+}
+
+/// The backups of every file in `folder`, by file name and newest first.
+pub fn folder_backups(folder: impl AsRef<Path>) -> Result<Vec<Backup>, TaikoError> {
+    let folder = folder.as_ref();
+    let backup_folder = folder.join(BACKUP_FOLDER);
+    if !backup_folder.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut found = Vec::<Backup>::new();
+    let entries = fs::read_dir(&backup_folder).map_err(TaikoError::io(&backup_folder))?;
+    for entry in entries {
+        let path = entry.map_err(TaikoError::io(&backup_folder))?.path();
+        let Some(name) = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+        else {
+            continue;
+        };
+        if let Some((original, stamp)) = parse_name(&name) {
+            found.push(Backup {
+                original: folder.join(original),
+                path,
+                stamp,
+            });
+        }
+    }
+
+    found.sort_by(|a, b| a.original.cmp(&b.original).then(b.stamp.cmp(&a.stamp)));
+    Ok(found)
+}
+
+fn backup_folder(path: &Path) -> Option<PathBuf> {
+    path.parent().map(|folder| folder.join(BACKUP_FOLDER))
+}
+
+/// Splits `<stem>.<stamp>[.<extension>]` into the original file name and the stamp.
+fn parse_name(name: &str) -> Option<(String, String)> {
+    let is_stamp = |s: &str| {
+        s.len() == 19
+            && s.char_indices().all(|(i, c)| match i {
+                8 | 15 => c == '-',
+                _ => c.is_ascii_digit(),
+            })
+    };
+
+    let (rest, last) = name.rsplit_once('.')?;
+    if is_stamp(last) {
+        return Some((rest.to_string(), last.to_string()));
+    }
+    let (stem, stamp) = rest.rsplit_once('.')?;
+    is_stamp(stamp).then(|| (format!("{}.{}", stem, last), stamp.to_string()))
+}
+
+/// Formats a time as `YYYYMMDD-HHMMSS-mmm` in UTC.
+fn stamp(time: SystemTime) -> String {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis())
+        .unwrap_or_default() as i64;
+    let (days, ms_of_day) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+
+    // Days since 1970-01-01 to a civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}",
+        year,
+        month,
+        day,
+        ms_of_day / 3_600_000,
+        ms_of_day / 60_000 % 60,
+        ms_of_day / 1000 % 60,
+        ms_of_day % 1000
+    )
+}
@@ -0,0 +1,108 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use crate::error::TaikoError;
+
+/// Finds every file below `root` whose name matches `pattern`, sorted by path.
+///
+/// The pattern is matched against file names only and ignores case. `*` matches any run of
+/// characters and `?` a single character, e.g. `*.mid` or `drums*.mid`. Symlinked folders
+/// are not entered, as they may link back to a folder above them.
+pub fn find_sources(root: impl AsRef<Path>, pattern: &str) -> Result<Vec<PathBuf>, TaikoError> {
+    let mut found = Vec::<PathBuf>::new();
+    let mut folders = vec![root.as_ref().to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let entries = std::fs::read_dir(&folder).map_err(TaikoError::io(&folder))?;
+        for entry in entries {
+            let entry = entry.map_err(TaikoError::io(&folder))?;
+            let path = entry.path();
+            if path.is_dir() {
+                let linked = entry
+                    .file_type()
+                    .map_err(TaikoError::io(&path))?
+                    .is_symlink();
+                if !linked {
+                    folders.push(path);
+                }
+            } else if path
+                .file_name()
+                .is_some_and(|name| matches_pattern(pattern, &name.to_string_lossy()))
+            {
+                found.push(path);
+            }
+        }
+    }
+
+    found.sort();
+    Ok(found)
+}
+
+/// Whether `name` matches the `*` and `?` wildcards of `pattern`, ignoring case.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    // Where the last `*` was seen, and where in the name it currently ends.
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more character and try again.
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Runs `job` on every item with up to `jobs` threads and returns the results in the order
+/// of `items`.
+pub fn run_parallel<T, R, F>(items: &[T], jobs: usize, job: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                let result = job(item);
+                results.lock().expect("a batch job panicked")[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("a batch job panicked")
+        .into_iter()
+        .map(|result| result.expect("every item was run"))
+        .collect()
+}
@@ -1,11 +1,24 @@
-use midly::{MetaMessage, Smf, TrackEvent, TrackEventKind};
+use midly::{
+    num::{u15, u24, u28, u4, u7},
+    Format, Fps, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+};
 use serde_json::Value;
-use std::{str, vec};
+use std::{borrow::Cow, path::Path, str, vec};
 
+use crate::error::TaikoError;
 use crate::json_structures::{
-    custom::Config,
+    custom::{Config, InputFormat, LanePitches, TimeSignature, UnmappedNotePolicy},
     edda_objects::{Bpmchange, CustomData, Note, Root},
 };
+use crate::reaper::RppProject;
+
+/// Name of the drum track in Rock Band and Clone Hero charts.
+const ROCK_BAND_DRUM_TRACK: &str = "PART DRUMS";
+/// Difficulty tiers of a Rock Band drum track with the kick pitch each tier starts at.
+/// Kick, red, yellow, blue and green pads follow on consecutive pitches.
+const ROCK_BAND_TIERS: [(&str, u8); 4] =
+    [("Easy", 60), ("Medium", 72), ("Hard", 84), ("Expert", 96)];
+const ROCK_BAND_TIER_WIDTH: u8 = 5;
 
 #[derive(Copy, Clone)]
 struct StampedEvent<'a> {
@@ -22,24 +35,181 @@ struct TrackAsOffsets<'a> {
     offsets: Vec<OffsetEvent<'a>>,
 }
 
+/// Tempo and time signature changes of the whole file, used to place ticks on the beat grid.
+///
+/// A beat is the note value of the time signature's denominator: a quarter note in 4/4
+/// and 3/4, an eighth note in 6/8, 7/8 and 12/8. Bars therefore always hold `numerator`
+/// beats, and bpm values are scaled to count those beats.
+struct TempoMap {
+    timing: Timing,
+    /// Absolute tick and microseconds per quarter note of every tempo change.
+    tempos: Vec<(u64, f64)>,
+    /// Absolute tick, numerator and denominator (as a power of two) of every time signature.
+    meters: Vec<(u64, u8, u8)>,
+}
+
+impl TempoMap {
+    /// The midi default of 120 bpm, used before the first tempo event.
+    const DEFAULT_TEMPO: f64 = 500_000_f64;
+    /// The midi default of 4/4, used before the first time signature.
+    const DEFAULT_METER: (u8, u8) = (4, 2);
+
+    fn new(timing: Timing, meta_events: &[StampedEvent]) -> Self {
+        let mut tempos = Vec::<(u64, f64)>::new();
+        let mut meters = Vec::<(u64, u8, u8)>::new();
+        for e in meta_events.iter() {
+            match e.event.kind {
+                TrackEventKind::Meta(MetaMessage::Tempo(tempo)) => {
+                    tempos.push((e.ticks_elapsed, tempo.as_int() as f64))
+                }
+                TrackEventKind::Meta(MetaMessage::TimeSignature(numerator, denominator, _, _)) => {
+                    meters.push((e.ticks_elapsed, numerator.max(1), denominator.min(6)))
+                }
+                _ => {}
+            }
+        }
+        tempos.sort_by_key(|t| t.0);
+        meters.sort_by_key(|m| m.0);
+
+        TempoMap {
+            timing,
+            tempos,
+            meters,
+        }
+    }
+
+    fn has_tempo(&self) -> bool {
+        !self.tempos.is_empty()
+    }
+
+    fn has_time_signature(&self) -> bool {
+        !self.meters.is_empty()
+    }
+
+    /// Uses a constant `bpm` for a file without tempo events.
+    fn with_constant_bpm(mut self, bpm: f64) -> Self {
+        self.tempos = vec![(0, 60_000_000_f64 / bpm)];
+        self
+    }
+
+    /// Uses a constant time signature for a file without time signature events.
+    fn with_time_signature(mut self, time_signature: TimeSignature) -> Self {
+        let denominator = time_signature.denominator.trailing_zeros() as u8;
+        self.meters = vec![(0, time_signature.numerator.max(1), denominator.min(6))];
+        self
+    }
+
+    /// Microseconds per quarter note at `ticks`.
+    fn tempo_at(&self, ticks: u64) -> f64 {
+        self.tempos
+            .iter()
+            .take_while(|t| t.0 <= ticks)
+            .last()
+            .or(self.tempos.first())
+            .map_or(TempoMap::DEFAULT_TEMPO, |t| t.1)
+    }
+
+    /// Numerator and denominator power of the time signature at `ticks`.
+    fn meter_at(&self, ticks: u64) -> (u8, u8) {
+        self.meters
+            .iter()
+            .take_while(|m| m.0 <= ticks)
+            .last()
+            .map_or(TempoMap::DEFAULT_METER, |m| (m.1, m.2))
+    }
+
+    /// The Edda bpm change that applies from `ticks` on, placed at `time`.
+    fn bpm_change_at(&self, ticks: u64, time: f64) -> Bpmchange {
+        let (numerator, denominator) = self.meter_at(ticks);
+        let quarter_bpm = 60_000_000_f64 / self.tempo_at(ticks);
+
+        Bpmchange {
+            bpm: quarter_bpm * TempoMap::beats_per_quarter(denominator),
+            time,
+            beats_per_bar: numerator as i64,
+            metronome_offset: TempoMap::metronome_offset(numerator, denominator),
+        }
+    }
+
+    /// Beats of the given denominator power in a quarter note, e.g. 2 for eighths.
+    fn beats_per_quarter(denominator: u8) -> f64 {
+        (1_u32 << denominator) as f64 / 4_f64
+    }
+
+    /// Beats between accented metronome clicks. Compound meters (6/8, 9/8, 12/8, ...)
+    /// accent every dotted quarter, which is three beats; every other meter accents
+    /// the start of each bar.
+    fn metronome_offset(numerator: u8, denominator: u8) -> i64 {
+        if denominator >= 3 && numerator > 3 && numerator.is_multiple_of(3) {
+            3
+        } else {
+            numerator as i64
+        }
+    }
+
+    /// Global beat of an absolute tick.
+    fn beat_at(&self, ticks: u64) -> f64 {
+        match self.timing {
+            Timing::Metrical(ppq) => {
+                // Count ticks scaled by the beat length of each meter as an integer and
+                // divide once, so that long songs do not drift.
+                let mut scaled: u128 = 0;
+                let mut last_tick = 0_u64;
+                let mut denominator = TempoMap::DEFAULT_METER.1;
+                for &(change_tick, _, change_denominator) in self.meters.iter() {
+                    if change_tick >= ticks {
+                        break;
+                    }
+                    scaled += ((change_tick - last_tick) as u128) << denominator;
+                    last_tick = change_tick;
+                    denominator = change_denominator;
+                }
+                scaled += ((ticks - last_tick) as u128) << denominator;
+
+                scaled as f64 / (4 * ppq.as_int() as u128) as f64
+            }
+            Timing::Timecode(fps, subframes) => {
+                // Timecode ticks are fixed fractions of a second, so beats depend on the tempo.
+                let frames_per_second = match fps {
+                    Fps::Fps29 => 30_000_f64 / 1_001_f64,
+                    _ => fps.as_int() as f64,
+                };
+                let ticks_per_second = frames_per_second * subframes as f64;
+
+                let mut changes: Vec<u64> = self
+                    .tempos
+                    .iter()
+                    .map(|t| t.0)
+                    .chain(self.meters.iter().map(|m| m.0))
+                    .filter(|&tick| tick < ticks)
+                    .collect();
+                changes.sort_unstable();
+                changes.dedup();
+
+                let mut beats = 0_f64;
+                let mut last_tick = 0_u64;
+                for change_tick in changes.into_iter().chain(std::iter::once(ticks)) {
+                    let seconds = (change_tick - last_tick) as f64 / ticks_per_second;
+                    let quarters = seconds * 1_000_000_f64 / self.tempo_at(last_tick);
+                    beats += quarters * TempoMap::beats_per_quarter(self.meter_at(last_tick).1);
+                    last_tick = change_tick;
+                }
+
+                beats
+            }
+        }
+    }
+}
+
 impl TrackAsOffsets<'_> {
     fn track_name(&self) -> Option<String> {
         for offset in self.offsets.iter() {
-            match offset.event.kind {
-                TrackEventKind::Meta(m) => match m {
-                    MetaMessage::TrackName(name) => {
-                        return match str::from_utf8(name) {
-                            Ok(name) => Some(name.to_string()),
-                            Err(_) => None,
-                        };
-                    }
-                    _ => {}
-                },
-                _ => {}
+            if let TrackEventKind::Meta(MetaMessage::TrackName(name)) = offset.event.kind {
+                return str::from_utf8(name).ok().map(|name| name.to_string());
             }
         }
 
-        return None;
+        None
     }
 }
 
@@ -49,14 +219,14 @@ impl StampedEvent<'_> {
         for vec in events {
             let mut valid_events: Vec<StampedEvent> = vec
                 .iter()
-                .map(|e| *e)
-                .filter(|e| match e.event.kind {
-                    TrackEventKind::Meta(m) => match m {
-                        MetaMessage::Tempo(_) => true,
-                        MetaMessage::TimeSignature(_, _, _, _) => true,
-                        _ => false,
-                    },
-                    _ => false,
+                .copied()
+                .filter(|e| {
+                    matches!(
+                        e.event.kind,
+                        TrackEventKind::Meta(
+                            MetaMessage::Tempo(_) | MetaMessage::TimeSignature(..)
+                        )
+                    )
                 })
                 .collect::<Vec<StampedEvent>>();
 
@@ -78,36 +248,153 @@ impl StampedEvent<'_> {
     }
 
     fn is_data_track(track: &Vec<StampedEvent>) -> bool {
-        for d in track {
-            match d.event.kind {
-                TrackEventKind::Midi {
-                    channel: _,
-                    message: _,
-                } => {
-                    return false;
-                }
-                _ => {}
-            };
+        !track
+            .iter()
+            .any(|d| matches!(d.event.kind, TrackEventKind::Midi { .. }))
+    }
+}
+
+/// A note whose pitch is not in the drum map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnmappedNote {
+    pub track: String,
+    pub pitch: u8,
+    pub bar: u64,
+    pub beat: f64,
+    /// Lane the note was moved to, or None if it was skipped.
+    pub lane: Option<usize>,
+}
+
+impl std::fmt::Display for UnmappedNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pitch {} on track {} at bar {}, beat {}",
+            self.pitch, self.track, self.bar, self.beat
+        )
+    }
+}
+
+/// Things worth telling the user about a conversion that did not stop it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionReport {
+    pub unmapped: Vec<UnmappedNote>,
+    /// Set when the file has no tempo events and the fallback bpm was used.
+    pub fallback_bpm: Option<f64>,
+    /// Set when the file has no time signature events and a fallback was used.
+    pub fallback_time_signature: Option<TimeSignature>,
+    /// Tracks with notes below their velocity threshold, and how many were left out.
+    pub soft_notes: Vec<(String, usize)>,
+    /// Tracks with notes that their time offset moved before the first beat, and how many
+    /// were left out.
+    pub early_notes: Vec<(String, usize)>,
+}
+
+impl ConversionReport {
+    pub fn is_empty(&self) -> bool {
+        self.unmapped.is_empty()
+            && self.fallback_bpm.is_none()
+            && self.fallback_time_signature.is_none()
+            && self.soft_notes.is_empty()
+            && self.early_notes.is_empty()
+    }
+
+    /// One line per fallback and per unmapped note, with a summary line before the notes
+    /// that were moved and before those that were skipped.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::<String>::new();
+        if let Some(bpm) = self.fallback_bpm {
+            lines.push(format!("No tempo events found, using {} bpm", bpm));
+        }
+        if let Some(time_signature) = self.fallback_time_signature {
+            lines.push(format!(
+                "No time signature events found, using {}",
+                time_signature
+            ));
+        }
+        for (track, count) in self.soft_notes.iter() {
+            lines.push(format!(
+                "{} notes on track {} were below its velocity threshold and left out",
+                count, track
+            ));
+        }
+        for (track, count) in self.early_notes.iter() {
+            lines.push(format!(
+                "{} notes on track {} were moved before the first beat by its time offset and left out",
+                count, track
+            ));
+        }
+        let (moved, skipped): (Vec<_>, Vec<_>) =
+            self.unmapped.iter().partition(|n| n.lane.is_some());
+        for (notes, action) in [(moved, "moved to the nearest lane"), (skipped, "skipped")] {
+            if !notes.is_empty() {
+                lines.push(format!(
+                    "{} notes not in the drum map were {}:",
+                    notes.len(),
+                    action
+                ));
+                lines.extend(notes.iter().map(|n| format!("  {}", n)));
+            }
         }
 
-        return true;
+        lines
     }
 }
 
+/// The beatmaps converted from every note track, named after the track.
+pub struct Conversion {
+    pub difficulties: Vec<(String, Root)>,
+    pub report: ConversionReport,
+}
+
+/// Converts midi files into Edda beatmaps.
+///
+/// ```no_run
+/// # fn main() -> Result<(), taiko::TaikoError> {
+/// let config = taiko::json_structures::custom::Config::default();
+/// let bytes = std::fs::read("song.mid").unwrap();
+/// let conversion = taiko::MidiConverter::from_bytes(&bytes)
+///     .with_config(&config)
+///     .convert()?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct MidiConverter<'a> {
-    source: String,
-    configuration: &'a Config,
+    bytes: Cow<'a, [u8]>,
+    configuration: Cow<'a, Config>,
 }
 
 impl<'a> MidiConverter<'a> {
-    pub fn new(source: String, configuration: &'a Config) -> Self {
+    /// Creates a converter over an in-memory midi file using the default config.
+    pub fn from_bytes(bytes: impl Into<Cow<'a, [u8]>>) -> Self {
         MidiConverter {
-            source,
-            configuration,
+            bytes: bytes.into(),
+            configuration: Cow::Owned(Config::default()),
         }
     }
 
-    fn track_to_stamped(track: &Vec<TrackEvent<'a>>) -> Vec<StampedEvent<'a>> {
+    /// Reads the midi file at `path` into a converter. REAPER projects (.rpp) are read
+    /// through [`RppProject`] and converted like a midi file of their tracks.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TaikoError> {
+        let is_rpp = path
+            .as_ref()
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("rpp"));
+        if is_rpp {
+            return Ok(MidiConverter::from_bytes(
+                RppProject::from_file(path)?.to_midi()?,
+            ));
+        }
+        let bytes = std::fs::read(path.as_ref()).map_err(TaikoError::io(path))?;
+        Ok(MidiConverter::from_bytes(bytes))
+    }
+
+    pub fn with_config(mut self, configuration: &'a Config) -> Self {
+        self.configuration = Cow::Borrowed(configuration);
+        self
+    }
+
+    fn track_to_stamped<'b>(track: &[TrackEvent<'b>]) -> Vec<StampedEvent<'b>> {
         let mut total_ticks: u64 = 0;
         let mut ret = Vec::<StampedEvent>::new();
 
@@ -115,7 +402,7 @@ impl<'a> MidiConverter<'a> {
             total_ticks += event.delta.as_int() as u64;
 
             let stamped = StampedEvent {
-                event: event.clone(),
+                event: *event,
                 ticks_elapsed: total_ticks,
             };
 
@@ -125,33 +412,49 @@ impl<'a> MidiConverter<'a> {
         ret
     }
 
-    pub fn to_root_merge_notes_and_meta(&self) -> Result<Root, &'static str> {
-        if let Ok(vecs) = self.to_root_merge_meta() {
-            let strip_name: Vec<Root> = vecs.into_iter().map(|v| v.1).collect();
-            if let Some(merged) = Root::merge_note_events_vec(&strip_name) {
-                return Ok(merged);
-            }
-        }
-        Err("Failed to merge midi tracks")
+    /// Converts every note track and merges the notes into a single beatmap.
+    pub fn convert_merged(&self) -> Result<(Root, ConversionReport), TaikoError> {
+        let conversion = self.convert()?;
+        let strip_name: Vec<Root> = conversion.difficulties.into_iter().map(|v| v.1).collect();
+        let merged = Root::merge_note_events_vec(&strip_name).ok_or(TaikoError::EmptyTrack)?;
+        Ok((merged, conversion.report))
     }
 
-    pub fn to_root_merge_meta(&self) -> Result<Vec<(String, Root)>, &'static str> {
-        let buf = std::fs::read(self.source.clone())
-            .expect("Failed to read source midi file. Please make sure the path exists.");
-        let smf = midly::Smf::parse(&buf).unwrap();
+    /// Converts every note track into its own beatmap, named after the track.
+    pub fn convert(&self) -> Result<Conversion, TaikoError> {
+        self.configuration
+            .validate()
+            .map_err(TaikoError::InvalidSetting)?;
+        let smf = midly::Smf::parse(&self.bytes)?;
 
         let mut track_as_stamped = Vec::<Vec<StampedEvent>>::new();
         for track in smf.tracks.iter() {
-            track_as_stamped.push(MidiConverter::track_to_stamped(&track));
+            track_as_stamped.push(MidiConverter::track_to_stamped(track));
         }
 
         let meta_events = StampedEvent::get_meta_events(&track_as_stamped);
+        let mut tempo_map = TempoMap::new(smf.header.timing, &meta_events);
+        let mut report = ConversionReport::default();
+        if !tempo_map.has_tempo() {
+            let bpm = self.configuration.fallback_bpm.ok_or(TaikoError::NoTempo)?;
+            tempo_map = tempo_map.with_constant_bpm(bpm);
+            report.fallback_bpm = Some(bpm);
+        }
+        if !tempo_map.has_time_signature() {
+            let time_signature = self
+                .configuration
+                .fallback_time_signature
+                .unwrap_or_default();
+            tempo_map = tempo_map.with_time_signature(time_signature);
+            report.fallback_time_signature = Some(time_signature);
+        }
 
         // Remove data track if it exists
-        track_as_stamped = track_as_stamped
-            .into_iter()
-            .filter(|t| !StampedEvent::is_data_track(t))
-            .collect();
+        track_as_stamped.retain(|t| !StampedEvent::is_data_track(t));
+
+        if track_as_stamped.is_empty() {
+            return Err(TaikoError::EmptyTrack);
+        }
 
         for stamped_track in track_as_stamped.iter_mut() {
             *stamped_track =
@@ -180,148 +483,208 @@ impl<'a> MidiConverter<'a> {
                 });
             }
 
-            tracks_as_offsets.push(TrackAsOffsets { offsets: offsets });
+            tracks_as_offsets.push(TrackAsOffsets { offsets });
+        }
+
+        if self.configuration.input_format == InputFormat::RockBand {
+            let roots = self.rock_band_difficulties(&tracks_as_offsets, &tempo_map, &mut report)?;
+            return Ok(Conversion {
+                difficulties: roots,
+                report,
+            });
         }
 
         let mut roots = Vec::<(String, Root)>::new();
         for (i, track) in tracks_as_offsets.iter().enumerate() {
-            match self.track_to_root_from_offsets(track, &smf) {
-                Ok(root) => {
-                    let name = match track.track_name() {
-                        Some(name) => name,
-                        None => match i {
-                            0 => "Easy",
-                            1 => "Normal",
-                            2 => "Hard",
-                            _ => "OutOfBounds",
-                        }
-                        .to_string(),
-                    };
-                    roots.push((name, root));
-                }
-                Err(e) => {
-                    eprintln!("{}", e);
+            let name = match track.track_name() {
+                Some(name) => name,
+                None => match i {
+                    0 => "Easy",
+                    1 => "Normal",
+                    2 => "Hard",
+                    _ => "OutOfBounds",
                 }
+                .to_string(),
+            };
+            let root =
+                self.track_to_root_from_offsets(track, &name, None, &tempo_map, &mut report)?;
+            roots.push((name, root));
+        }
+
+        Ok(Conversion {
+            difficulties: roots,
+            report,
+        })
+    }
+
+    /// Splits the Rock Band drum track into one beatmap per difficulty tier that has notes.
+    /// Each tier's pitches are moved onto the Easy tier's (60 to 64) before the drum map
+    /// is applied, so one drum map covers every tier.
+    fn rock_band_difficulties(
+        &self,
+        tracks: &[TrackAsOffsets],
+        tempo_map: &TempoMap,
+        report: &mut ConversionReport,
+    ) -> Result<Vec<(String, Root)>, TaikoError> {
+        let drums = tracks
+            .iter()
+            .find(|track| {
+                track
+                    .track_name()
+                    .is_some_and(|name| name.trim().eq_ignore_ascii_case(ROCK_BAND_DRUM_TRACK))
+            })
+            .ok_or_else(|| TaikoError::TrackNotFound(ROCK_BAND_DRUM_TRACK.to_string()))?;
+
+        let mut roots = Vec::<(String, Root)>::new();
+        for (name, kick) in ROCK_BAND_TIERS {
+            let root =
+                self.track_to_root_from_offsets(drums, name, Some(kick), tempo_map, report)?;
+            if !root.notes.is_empty() {
+                roots.push((name.to_string(), root));
+            }
+        }
+
+        if roots.is_empty() {
+            return Err(TaikoError::EmptyTrack);
+        }
+
+        Ok(roots)
+    }
+
+    /// Finds the lane for `key`, applying the unmapped note policy when it is not in the map.
+    fn lane_for_key(
+        &self,
+        key: u8,
+        drum_map: &[LanePitches],
+        track_name: &str,
+        global_beat: f64,
+        bpm_changes: &[Bpmchange],
+        report: &mut ConversionReport,
+    ) -> Result<Option<usize>, TaikoError> {
+        if let Some(lane) = drum_map.iter().position(|lane| lane.contains(key)) {
+            return Ok(Some(lane));
+        }
+
+        let (bar, beat) = Root::bar_and_beat(bpm_changes, global_beat);
+        let mut unmapped = UnmappedNote {
+            track: track_name.to_string(),
+            pitch: key,
+            bar,
+            beat,
+            lane: None,
+        };
+
+        match self.configuration.unmapped_notes {
+            UnmappedNotePolicy::Error => return Err(TaikoError::UnmappedNote(unmapped)),
+            UnmappedNotePolicy::Skip => {}
+            // Skipped after all if the drum map has no pitches to be near to.
+            UnmappedNotePolicy::Nearest => {
+                unmapped.lane = drum_map
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(lane, pitches)| Some((lane, pitches.distance(key)?)))
+                    .min_by_key(|&(_, distance)| distance)
+                    .map(|(lane, _)| lane);
             }
         }
+        let lane = unmapped.lane;
+        report.unmapped.push(unmapped);
+        Ok(lane)
+    }
 
-        return Ok(roots);
+    /// Global beat of an absolute tick, computed from the tick in one go so that long
+    /// songs do not drift, then rounded to the configured precision.
+    fn beat_at(&self, ticks: u64, tempo_map: &TempoMap) -> f64 {
+        round_to(tempo_map.beat_at(ticks), self.configuration.time_precision)
     }
 
+    /// Converts one track, with the drum map, velocity threshold and time offset of its
+    /// entry in `track_overrides` if it has one.
     fn track_to_root_from_offsets(
         &self,
         track: &TrackAsOffsets,
-        smf: &Smf,
-    ) -> Result<Root, &'static str> {
+        track_name: &str,
+        rock_band_tier: Option<u8>,
+        tempo_map: &TempoMap,
+        report: &mut ConversionReport,
+    ) -> Result<Root, TaikoError> {
+        let settings = self
+            .configuration
+            .track_settings(track_name)
+            .cloned()
+            .unwrap_or_default();
+        let drum_map = self.configuration.drum_map_for(track_name);
+        let mut soft_notes = 0;
+        let mut early_notes = 0;
         let mut stamped_hits: Vec<Note> = vec![];
-        let mut bpm_changes: Vec<Bpmchange> = vec![];
+        // Tempo and time signature events at the first tick replace this change.
+        let mut bpm_changes: Vec<Bpmchange> = vec![tempo_map.bpm_change_at(0, 0_f64)];
         let mut ticks_elapsed: u64 = 0;
 
-        let mut tick_len: f64 = 0_f64;
-        let mut current_beat_len: f64 = 0.0;
-        let mut global_beat_accumulator: f64 = 0.0;
-
         for offset in track.offsets.iter() {
-            ticks_elapsed = ticks_elapsed + offset.delta;
-            let _time_accumulator = (ticks_elapsed as f64 * tick_len) / 1_000_000_f64;
-
-            global_beat_accumulator += if current_beat_len != 0.0 {
-                offset.delta as f64 / current_beat_len
-            } else {
-                0.0
-            };
+            ticks_elapsed += offset.delta;
+            let global_beat = self.beat_at(ticks_elapsed, tempo_map);
 
             match offset.event.kind {
-                midly::TrackEventKind::Midi {
-                    channel: _,
-                    message,
-                } => match message {
-                    midly::MidiMessage::NoteOn { key, vel } => {
-                        if vel != 0 {
+                TrackEventKind::Midi {
+                    message: MidiMessage::NoteOn { key, vel },
+                    ..
+                } => {
+                    // Notes of other tiers and chart markers are not part of the tier.
+                    let key = match rock_band_tier {
+                        Some(kick) => match key.as_int().checked_sub(kick) {
+                            Some(pad) if pad < ROCK_BAND_TIER_WIDTH => ROCK_BAND_TIERS[0].1 + pad,
+                            _ => continue,
+                        },
+                        None => key.as_int(),
+                    };
+                    let vel = vel.as_int();
+                    let time = global_beat + settings.time_offset;
+                    if vel != 0 && vel < settings.min_velocity {
+                        soft_notes += 1;
+                    } else if vel != 0 && time < 0_f64 {
+                        early_notes += 1;
+                    } else if vel != 0 {
+                        if let Some(lane) = self.lane_for_key(
+                            key,
+                            drum_map,
+                            track_name,
+                            global_beat,
+                            &bpm_changes,
+                            report,
+                        )? {
                             stamped_hits.push(Note {
-                                line_index: self
-                                    .configuration
-                                    .drum_map
-                                    .iter()
-                                    .position(|&r| r == key.as_int())
-                                    .unwrap_or(0)
-                                    as i64,
-                                time: global_beat_accumulator,
+                                line_index: lane as i64,
+                                time: round_to(time, self.configuration.time_precision),
                                 line_layer: 1,
                                 type_field: 0,
                                 cut_direction: 1,
                             });
                         }
                     }
-                    _ => {}
-                },
-                midly::TrackEventKind::SysEx(_) => {}
-                midly::TrackEventKind::Escape(_) => {}
-                midly::TrackEventKind::Meta(m) => match m {
-                    MetaMessage::Tempo(tempo) => {
-                        let mut beat_len: f64 = 0.0;
-                        tick_len = match smf.header.timing {
-                            midly::Timing::Metrical(m) => {
-                                current_beat_len = m.as_int() as f64;
-                                let microseconds = tempo.as_int() as f64 / m.as_int() as f64;
-                                beat_len = m.as_int() as f64 * microseconds;
-                                microseconds.into()
-                            }
-                            midly::Timing::Timecode(_f, _u) => {
-                                println!("Timecode mode not tested. Results may not be correct. Use metrical for best results.");
-                                1.0 / _f.as_f32() as f64 / _u as f64
-                            }
-                        };
-
-                        let bpm = 60_f64 / (beat_len / 1_000_000_f64);
-
-                        if let Some(change) = bpm_changes.last_mut() {
-                            if change.time == global_beat_accumulator {
-                                change.bpm = bpm;
-                            } else {
-                                let mut copy = change.clone();
-                                copy.bpm = bpm;
-                                copy.time = global_beat_accumulator;
-                                bpm_changes.push(copy);
-                            }
-                        } else {
-                            let change = Bpmchange {
-                                bpm: bpm,
-                                time: global_beat_accumulator,
-                                beats_per_bar: 4,
-                                metronome_offset: 4,
-                            };
-                            bpm_changes.push(change);
-                        }
-                    }
-                    MetaMessage::TimeSignature(numerator, _denominator, _clocks_per_click, _b) => {
-                        if let Some(change) = bpm_changes.last_mut() {
-                            if change.time == global_beat_accumulator {
-                                change.beats_per_bar = numerator as i64;
-                                change.metronome_offset = numerator as i64;
-                                // I don't know what metranome offset is. Relevant? Idk.
-                            } else {
-                                let mut copy = change.clone();
-                                copy.beats_per_bar = numerator as i64;
-                                copy.metronome_offset = numerator as i64;
-                                copy.time = global_beat_accumulator;
-                                bpm_changes.push(copy);
-                            }
-                        } else {
-                            let change = Bpmchange {
-                                bpm: 120_f64,
-                                time: global_beat_accumulator,
-                                beats_per_bar: numerator as i64,
-                                metronome_offset: numerator as i64,
-                            };
-                            bpm_changes.push(change);
-                        }
+                }
+                TrackEventKind::Meta(MetaMessage::Tempo(_) | MetaMessage::TimeSignature(..)) => {
+                    // Both events start a new bpm change; the tempo map knows the
+                    // tempo and meter in effect from this tick on.
+                    let change = tempo_map.bpm_change_at(ticks_elapsed, global_beat);
+                    match bpm_changes.last_mut() {
+                        Some(last) if last.time == global_beat => *last = change,
+                        _ => bpm_changes.push(change),
                     }
-                    _ => {}
-                },
+                }
+                _ => {}
             }
         }
 
+        if soft_notes > 0 {
+            report.soft_notes.push((track_name.to_string(), soft_notes));
+        }
+        if early_notes > 0 {
+            report
+                .early_notes
+                .push((track_name.to_string(), early_notes));
+        }
+
         let json_data = Root {
             version: "1".to_string(),
             custom_data: CustomData {
@@ -337,3 +700,226 @@ impl<'a> MidiConverter<'a> {
         Ok(json_data)
     }
 }
+
+/// Rounds `value` to `precision` decimal places.
+pub(crate) fn round_to(value: f64, precision: u32) -> f64 {
+    let scale = 10_f64.powi(precision.min(Config::MAX_TIME_PRECISION) as i32);
+    (value * scale).round() / scale
+}
+
+/// A beatmap note that has no pitch to go back to, as its lane is not in the drum map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedNote {
+    pub beat: f64,
+    pub line_index: i64,
+}
+
+impl std::fmt::Display for SkippedNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "note at beat {}: line index {} is not in the drum map",
+            self.beat, self.line_index
+        )
+    }
+}
+
+/// A beatmap converted back into a midi file, with the notes that were left out.
+pub struct MidiConversion {
+    pub midi: Vec<u8>,
+    pub skipped: Vec<SkippedNote>,
+}
+
+/// Converts an Edda .dat file back into a standard midi file.
+pub struct DatConverter<'a> {
+    bytes: Cow<'a, [u8]>,
+    configuration: Cow<'a, Config>,
+    track_name: String,
+}
+
+impl<'a> DatConverter<'a> {
+    const TICKS_PER_BEAT: u16 = 480;
+    const NOTE_LENGTH: u64 = 60;
+    const DRUM_CHANNEL: u8 = 9;
+
+    /// Creates a converter over an in-memory .dat file using the default config.
+    pub fn from_bytes(bytes: impl Into<Cow<'a, [u8]>>) -> Self {
+        DatConverter {
+            bytes: bytes.into(),
+            configuration: Cow::Owned(Config::default()),
+            track_name: String::new(),
+        }
+    }
+
+    /// Reads the .dat file at `path` into a converter. The note track is named after the
+    /// file, so converting it back with `auto` keeps the difficulty name.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TaikoError> {
+        let bytes = std::fs::read(path.as_ref()).map_err(TaikoError::io(&path))?;
+        let track_name = path
+            .as_ref()
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(DatConverter::from_bytes(bytes).with_track_name(track_name))
+    }
+
+    pub fn with_config(mut self, configuration: &'a Config) -> Self {
+        self.configuration = Cow::Borrowed(configuration);
+        self
+    }
+
+    pub fn with_track_name(mut self, track_name: impl Into<String>) -> Self {
+        self.track_name = track_name.into();
+        self
+    }
+
+    /// Denominator power of the meter a bpm change was converted from. Edda maps do not
+    /// keep it, so compound meters, which accent every three beats, are read as eighths
+    /// and every other meter as quarters.
+    fn denominator(change: &Bpmchange) -> u8 {
+        let beats = change.beats_per_bar;
+        if change.metronome_offset == 3 && beats > 3 && beats % 3 == 0 {
+            3
+        } else {
+            2
+        }
+    }
+
+    /// Ticks of a global beat, counting the beats of each bpm change in its own beat unit.
+    fn beat_to_ticks(changes: &[Bpmchange], beat: f64) -> u64 {
+        let mut quarters = 0_f64;
+        let mut last_beat = 0_f64;
+        let mut beats_per_quarter = 1_f64;
+        for change in changes.iter() {
+            if change.time > beat {
+                break;
+            }
+            quarters += (change.time - last_beat) / beats_per_quarter;
+            last_beat = change.time;
+            beats_per_quarter = TempoMap::beats_per_quarter(DatConverter::denominator(change));
+        }
+        quarters += (beat - last_beat) / beats_per_quarter;
+
+        (quarters * DatConverter::TICKS_PER_BEAT as f64)
+            .round()
+            .max(0.0) as u64
+    }
+
+    pub(crate) fn to_track(mut stamped: Vec<(u64, TrackEventKind)>) -> Vec<TrackEvent> {
+        // Stable sort keeps note-offs (pushed first) ahead of note-ons on the same tick.
+        stamped.sort_by_key(|e| e.0);
+
+        let mut track = Vec::<TrackEvent>::new();
+        let mut last: u64 = 0;
+        for (ticks, kind) in stamped {
+            track.push(TrackEvent {
+                delta: u28::new((ticks - last) as u32),
+                kind,
+            });
+            last = ticks;
+        }
+        track.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+
+        track
+    }
+
+    /// Converts the beatmap into the bytes of a standard midi file.
+    pub fn convert(&self) -> Result<MidiConversion, TaikoError> {
+        let root: Root = serde_json::from_slice(&self.bytes)?;
+        let track_name = &self.track_name;
+
+        let changes = &root.custom_data.bpmchanges;
+        let mut meta = Vec::<(u64, TrackEventKind)>::new();
+        for change in changes.iter() {
+            let ticks = DatConverter::beat_to_ticks(changes, change.time);
+            let denominator = DatConverter::denominator(change);
+            if change.bpm > 0.0 {
+                // Edda counts the bpm in beats of the meter, midi in quarter notes.
+                let quarter_bpm = change.bpm / TempoMap::beats_per_quarter(denominator);
+                let tempo = (60_000_000_f64 / quarter_bpm).round() as u32;
+                meta.push((
+                    ticks,
+                    TrackEventKind::Meta(MetaMessage::Tempo(u24::new(tempo))),
+                ));
+            }
+            meta.push((
+                ticks,
+                TrackEventKind::Meta(MetaMessage::TimeSignature(
+                    change.beats_per_bar.clamp(1, 255) as u8,
+                    denominator,
+                    24,
+                    8,
+                )),
+            ));
+        }
+
+        let mut notes = vec![(
+            0,
+            TrackEventKind::Meta(MetaMessage::TrackName(track_name.as_bytes())),
+        )];
+        // Undo the track's time offset, and keep its notes above its velocity threshold.
+        let settings = self
+            .configuration
+            .track_settings(track_name)
+            .cloned()
+            .unwrap_or_default();
+        // Notes are written at 100, a usual drum velocity, unless the track's threshold is
+        // higher, so that they are not left out when converted again. A threshold above 127,
+        // the highest midi velocity, leaves out every note anyway.
+        let velocity = u7::new(settings.min_velocity.max(100).min(u7::max_value().as_int()));
+        let drum_map = self.configuration.drum_map_for(track_name);
+        let mut offs = Vec::<(u64, TrackEventKind)>::new();
+        let mut skipped = Vec::<SkippedNote>::new();
+        for note in root.notes.iter() {
+            let pitch = drum_map
+                .get(note.line_index as usize)
+                .and_then(|lane| lane.first_pitch());
+            let key = match pitch {
+                Some(pitch) => u7::new(pitch),
+                None => {
+                    skipped.push(SkippedNote {
+                        beat: note.time,
+                        line_index: note.line_index,
+                    });
+                    continue;
+                }
+            };
+
+            let ticks = DatConverter::beat_to_ticks(changes, note.time - settings.time_offset);
+            let channel = u4::new(DatConverter::DRUM_CHANNEL);
+            offs.push((
+                ticks + DatConverter::NOTE_LENGTH,
+                TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::NoteOff {
+                        key,
+                        vel: u7::new(0),
+                    },
+                },
+            ));
+            notes.push((
+                ticks,
+                TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::NoteOn { key, vel: velocity },
+                },
+            ));
+        }
+        offs.append(&mut notes);
+
+        let smf = Smf {
+            header: Header::new(
+                Format::Parallel,
+                Timing::Metrical(u15::new(DatConverter::TICKS_PER_BEAT)),
+            ),
+            tracks: vec![DatConverter::to_track(meta), DatConverter::to_track(offs)],
+        };
+
+        let mut midi = Vec::<u8>::new();
+        smf.write(&mut midi).map_err(TaikoError::MidiWrite)?;
+        Ok(MidiConversion { midi, skipped })
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use crate::converters::UnmappedNote;
+
+/// Everything that can go wrong while converting or writing beatmaps.
+#[derive(Debug)]
+pub enum TaikoError {
+    /// Reading or writing a file failed.
+    Io { path: PathBuf, source: io::Error },
+    /// The source file is not a valid midi file.
+    MidiParse(midly::Error),
+    /// A midi file could not be encoded.
+    MidiWrite(&'static str),
+    /// The midi file does not contain any tracks with notes.
+    EmptyTrack,
+    /// The midi file does not contain a tempo event.
+    NoTempo,
+    /// A json file could not be read or written.
+    Serialization(serde_json::Error),
+    /// A song package references files that are missing or misnamed.
+    InvalidPackage(String),
+    /// A note's pitch is not in the drum map and the policy is to fail.
+    UnmappedNote(UnmappedNote),
+    /// No built-in or saved drum map preset has the given name.
+    UnknownPreset(String),
+    /// The midi file has no track with the given name.
+    TrackNotFound(String),
+    /// A StepMania chart could not be read.
+    StepmaniaParse(String),
+    /// A REAPER project could not be read.
+    RppParse(String),
+    /// Some songs of a batch conversion failed.
+    BatchFailed { failed: usize, total: usize },
+    /// A file has no backup with the requested number.
+    BackupNotFound { path: PathBuf, number: usize },
+    /// No configuration profile has the given name.
+    UnknownProfile(String),
+    /// A profile cannot be created or deleted as asked.
+    ProfileConflict(String),
+    /// A taiko.json project file is not a valid set of config overrides.
+    InvalidProjectFile { path: PathBuf, message: String },
+    /// A setting given to `config` does not exist or its value is invalid.
+    InvalidSetting(String),
+    /// config.json could not be read. It is left alone; `backup` is a copy of it.
+    InvalidConfig {
+        path: PathBuf,
+        message: String,
+        backup: Option<PathBuf>,
+    },
+}
+
+impl TaikoError {
+    /// Returns a closure for `map_err` that attaches `path` to an io error.
+    pub fn io(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> TaikoError {
+        let path = path.as_ref().to_path_buf();
+        move |source| TaikoError::Io { path, source }
+    }
+
+    /// Process exit code used by the cli for this kind of failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TaikoError::Io { .. } => 3,
+            TaikoError::MidiParse(_) => 4,
+            TaikoError::EmptyTrack => 5,
+            TaikoError::NoTempo => 6,
+            TaikoError::Serialization(_) => 7,
+            TaikoError::InvalidPackage(_) => 8,
+            TaikoError::MidiWrite(_) => 9,
+            TaikoError::UnmappedNote(_) => 10,
+            TaikoError::UnknownPreset(_) => 11,
+            TaikoError::TrackNotFound(_) => 12,
+            TaikoError::StepmaniaParse(_) => 13,
+            TaikoError::RppParse(_) => 14,
+            TaikoError::BatchFailed { .. } => 15,
+            TaikoError::BackupNotFound { .. } => 16,
+            TaikoError::UnknownProfile(_) => 17,
+            TaikoError::ProfileConflict(_) => 18,
+            TaikoError::InvalidProjectFile { .. } => 19,
+            TaikoError::InvalidConfig { .. } => 20,
+            TaikoError::InvalidSetting(_) => 21,
+        }
+    }
+}
+
+impl fmt::Display for TaikoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaikoError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            TaikoError::MidiParse(e) => write!(f, "Failed to parse midi file: {}", e),
+            TaikoError::MidiWrite(e) => write!(f, "Failed to write midi file: {}", e),
+            TaikoError::EmptyTrack => write!(f, "The midi file does not contain any note tracks"),
+            TaikoError::NoTempo => write!(
+                f,
+                "The midi file does not contain a tempo event. Export it with tempo metadata, or set a fallback with --bpm or fallback_bpm in config.json"
+            ),
+            TaikoError::Serialization(e) => write!(f, "Invalid json: {}", e),
+            TaikoError::InvalidPackage(msg) => write!(f, "Invalid package: {}", msg),
+            TaikoError::UnmappedNote(note) => write!(f, "Unmapped note: {}", note),
+            TaikoError::UnknownPreset(name) => write!(f, "No drum map preset named \"{}\"", name),
+            TaikoError::UnknownProfile(name) => write!(f, "No profile named \"{}\"", name),
+            TaikoError::ProfileConflict(msg) => write!(f, "{}", msg),
+            TaikoError::InvalidProjectFile { path, message } => {
+                write!(f, "Invalid project file {}: {}", path.display(), message)
+            }
+            TaikoError::InvalidSetting(msg) => write!(f, "Invalid setting: {}", msg),
+            TaikoError::InvalidConfig {
+                path,
+                message,
+                backup,
+            } => {
+                write!(
+                    f,
+                    "Could not read the config {}: {}. It was left unchanged",
+                    path.display(),
+                    message
+                )?;
+                if let Some(backup) = backup {
+                    write!(f, " and a copy was saved to {}", backup.display())?;
+                }
+                write!(f, ". Fix it, or delete it to start over with the default settings")
+            }
+            TaikoError::StepmaniaParse(msg) => write!(f, "Failed to read StepMania chart: {}", msg),
+            TaikoError::RppParse(msg) => write!(f, "Failed to read REAPER project: {}", msg),
+            TaikoError::BatchFailed { failed, total } => {
+                write!(f, "{} of {} songs failed to convert", failed, total)
+            }
+            TaikoError::BackupNotFound { path, number } => write!(
+                f,
+                "{} has no backup {}. List its backups with taiko restore --list",
+                path.display(),
+                number
+            ),
+            TaikoError::TrackNotFound(name) => {
+                write!(f, "The midi file does not contain a \"{}\" track", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TaikoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TaikoError::Io { source, .. } => Some(source),
+            TaikoError::MidiParse(e) => Some(e),
+            TaikoError::Serialization(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<midly::Error> for TaikoError {
+    fn from(e: midly::Error) -> Self {
+        TaikoError::MidiParse(e)
+    }
+}
+
+impl From<serde_json::Error> for TaikoError {
+    fn from(e: serde_json::Error) -> Self {
+        TaikoError::Serialization(e)
+    }
+}
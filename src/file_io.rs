@@ -1,49 +1,140 @@
 use std::{
-    fs::{File, OpenOptions},
+    fs::File,
     io::Write,
-    path::Path,
+    path::{Path, PathBuf},
 };
 
-use crate::json_structures::custom::Config;
+use crate::{
+    backup,
+    error::TaikoError,
+    json_structures::{
+        custom::{BeatRange, Config, ProfileStore},
+        edda_info,
+        edda_objects::Root,
+    },
+};
 
-pub fn get_or_create_file_rw(path: &Path) -> Result<File, std::io::Error> {
-    if !path.exists() {
-        OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(path)
-    } else {
-        OpenOptions::new().read(true).write(true).open(path)
+/// Name of the file that overrides the config for every song in its folder and below.
+pub const PROJECT_FILE: &str = "taiko.json";
+
+pub fn write_output_json<T: serde::Serialize>(path: &String, data: &T) -> Result<(), TaikoError> {
+    let json = serde_json::to_string_pretty(data)?;
+    let mut file = File::create(path).map_err(TaikoError::io(path))?;
+    file.write_all(json.as_bytes())
+        .map_err(TaikoError::io(path))
+}
+
+/// Writes `converted` to `path` like [`write_output_json`], but when the file already exists
+/// only its notes and bpm changes (within `range`, if given) are replaced, so events,
+/// obstacles and bookmarks added in Edda are kept.
+pub fn update_output_json(
+    path: &String,
+    converted: &Root,
+    range: Option<BeatRange>,
+) -> Result<(), TaikoError> {
+    if !Path::new(path).exists() {
+        return write_output_json(path, converted);
     }
+
+    let existing = std::fs::read_to_string(path).map_err(TaikoError::io(path))?;
+    let mut root: Root = serde_json::from_str(&existing)?;
+    root.update_from(converted, range);
+    write_output_json(path, &root)
 }
 
-pub fn write_output_json(path: &String, data: &crate::json_structures::edda_objects::Root) {
-    let file_name = path;
-    match File::create(file_name) {
-        Ok(mut file) => {
-            println!("Writing to {} ...", file_name);
-            std::io::Write::write_all(
-                &mut file,
-                serde_json::to_string_pretty(data)
-                    .unwrap_or_default()
-                    .as_bytes(),
-            )
-            .expect("Failed to serialize data");
-        }
-        Err(e) => eprintln!("Failed to create file {}: {}", path, e),
+/// Writes a generated info.dat to `path`, or when one already exists replaces only its
+/// difficulties and bpm, so the song details set in Edda are kept.
+pub fn update_info_json(path: &String, generated: &edda_info::Root) -> Result<(), TaikoError> {
+    if !Path::new(path).exists() {
+        return write_output_json(path, generated);
     }
+
+    let existing = std::fs::read_to_string(path).map_err(TaikoError::io(path))?;
+    let mut info: edda_info::Root = serde_json::from_str(&existing)?;
+    info.update_from(generated);
+    write_output_json(path, &info)
 }
 
-pub fn save_config(config: &Config, file: &mut File) {
-    match serde_json::to_string_pretty(config) {
-        Ok(json_str) => {
-            file.set_len(0).expect("Failed to write config.");
-            file.write_all(json_str.as_bytes())
-                .expect("Failed to write config.");
-        }
-        Err(e) => {
-            eprintln!("{}", e);
+pub fn write_output_bytes(path: &String, data: &[u8]) -> Result<(), TaikoError> {
+    let mut file = File::create(path).map_err(TaikoError::io(path))?;
+    file.write_all(data).map_err(TaikoError::io(path))
+}
+
+/// Reads the profiles saved in the config file at `path`, migrating an older layout. A missing
+/// or empty file holds the default profile. A file that cannot be read is backed up and left
+/// as it is, so its settings are not lost.
+pub fn read_config(path: &Path) -> Result<ProfileStore, TaikoError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(TaikoError::io(path)(e)),
+    };
+    if text.trim().is_empty() {
+        return Ok(ProfileStore::default());
+    }
+
+    serde_json::from_str(&text).map_err(|e| TaikoError::InvalidConfig {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+        backup: back_up_config(path, &text),
+    })
+}
+
+/// Backs up a config that cannot be read, unless its newest backup is the same.
+fn back_up_config(path: &Path, text: &str) -> Option<PathBuf> {
+    let newest = backup::backups(path).ok()?.into_iter().next();
+    if let Some(newest) = newest {
+        if std::fs::read_to_string(&newest.path).ok()? == text {
+            return Some(newest.path);
         }
+    }
+    let generations = Config::default().backup_generations;
+    backup::back_up(path, generations)
+        .ok()
+        .flatten()
+        .map(|backup| backup.path)
+}
+
+/// Writes the profiles to the config file at `path`, creating its folder if needed.
+pub fn save_config(profiles: &ProfileStore, path: &Path) -> Result<(), TaikoError> {
+    let json_str = serde_json::to_string_pretty(profiles)?;
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder).map_err(TaikoError::io(folder))?;
+    }
+    std::fs::write(path, json_str).map_err(TaikoError::io(path))
+}
+
+/// Finds the project file nearest to `source`: in its folder, or the closest folder above.
+pub fn find_project_file(source: impl AsRef<Path>) -> Option<PathBuf> {
+    let source = source.as_ref();
+    let folder = if source.is_dir() {
+        source
+    } else {
+        source.parent()?
+    };
+    // A bare file name sits in the working directory.
+    let folder = if folder.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        folder
+    };
+    let folder = folder
+        .canonicalize()
+        .unwrap_or_else(|_| folder.to_path_buf());
+    folder
+        .ancestors()
+        .map(|folder| folder.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Reads the project file at `path` and applies its settings on top of `config`.
+pub fn apply_project_file(config: &Config, path: impl AsRef<Path>) -> Result<Config, TaikoError> {
+    let path = path.as_ref();
+    let invalid = |message: String| TaikoError::InvalidProjectFile {
+        path: path.to_path_buf(),
+        message,
     };
+    let text = std::fs::read_to_string(path).map_err(TaikoError::io(path))?;
+    let overrides = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    config.with_overrides(&overrides).map_err(invalid)
 }
@@ -3,6 +3,9 @@ pub mod edda_info {
     use serde_derive::Serialize;
     use serde_json::Value;
 
+    use super::custom::SongMetadata;
+    use super::edda_objects;
+
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Root {
@@ -44,6 +47,100 @@ pub mod edda_info {
         pub difficulty_beatmap_sets: Vec<DifficultyBeatmapSet>,
     }
 
+    impl Root {
+        /// Builds an info.dat listing every difficulty file in `beatmaps`, named
+        /// `<difficulty><extension>`. The bpm is taken from the first tempo event.
+        pub fn from_difficulties(
+            song: &SongMetadata,
+            beatmaps: &[(String, edda_objects::Root)],
+            extension: &str,
+        ) -> Root {
+            let beats_per_minute = beatmaps
+                .iter()
+                .find_map(|(_, root)| root.custom_data.bpmchanges.first())
+                .map(|change| change.bpm)
+                .unwrap_or(120_f64);
+
+            let duration = beatmaps
+                .iter()
+                .filter_map(|(_, root)| root.notes.last().map(|n| root.seconds_at(n.time)))
+                .fold(0_f64, f64::max);
+
+            let difficulty_beatmaps = beatmaps
+                .iter()
+                .enumerate()
+                .map(|(i, (name, _))| DifficultyBeatmap {
+                    difficulty: name.clone(),
+                    difficulty_rank: match name.as_str() {
+                        "Easy" => 1,
+                        "Normal" | "Medium" => 2,
+                        "Hard" => 3,
+                        "Expert" => 4,
+                        _ => i as i64 + 1,
+                    },
+                    beatmap_filename: format!("{}{}", name, extension),
+                    note_jump_movement_speed: 15_f64,
+                    note_jump_start_beat_offset: 0,
+                    custom_data: CustomData2 {
+                        editor_grid_spacing: 1_f64,
+                        editor_grid_division: 4,
+                        ..Default::default()
+                    },
+                })
+                .collect();
+
+            Root {
+                version: "1".to_string(),
+                song_name: song.song_name.clone(),
+                song_author_name: song.song_author_name.clone(),
+                explicit: "false".to_string(),
+                beats_per_minute,
+                shuffle_period: 0.5,
+                song_approximative_duration: duration.ceil() as i64,
+                song_filename: song.song_filename.clone(),
+                cover_image_filename: song.cover_image_filename.clone(),
+                environment_name: "Midgard".to_string(),
+                custom_data: CustomData {
+                    contributors: Vec::<Value>::new(),
+                    editors: Editors {
+                        edda: Edda::default(),
+                        last_edited_by: "taiko".to_string(),
+                    },
+                },
+                difficulty_beatmap_sets: vec![DifficultyBeatmapSet {
+                    beatmap_characteristic_name: "Standard".to_string(),
+                    difficulty_beatmaps,
+                }],
+                ..Default::default()
+            }
+        }
+
+        /// Replaces the difficulties and bpm of this info.dat with those of `generated`,
+        /// keeping the song details set in Edda. Difficulties it already lists keep their
+        /// settings, e.g. note jump speed.
+        pub fn update_from(&mut self, generated: &Root) {
+            let existing: Vec<DifficultyBeatmap> = self
+                .difficulty_beatmap_sets
+                .drain(..)
+                .flat_map(|set| set.difficulty_beatmaps)
+                .collect();
+            self.difficulty_beatmap_sets = generated.difficulty_beatmap_sets.clone();
+            for beatmap in self
+                .difficulty_beatmap_sets
+                .iter_mut()
+                .flat_map(|set| set.difficulty_beatmaps.iter_mut())
+            {
+                if let Some(kept) = existing
+                    .iter()
+                    .find(|kept| kept.beatmap_filename == beatmap.beatmap_filename)
+                {
+                    *beatmap = kept.clone();
+                }
+            }
+            self.beats_per_minute = generated.beats_per_minute;
+        }
+    }
+
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub struct CustomData {
@@ -117,6 +214,7 @@ pub mod edda_info {
 }
 
 pub mod edda_objects {
+    use super::custom::BeatRange;
     use serde_derive::Deserialize;
     use serde_derive::Serialize;
     use serde_json::Value;
@@ -148,11 +246,122 @@ pub mod edda_objects {
                 notes.sort_by(|i, j| i.time.partial_cmp(&j.time).unwrap());
                 merged.notes = notes;
 
-                return Some(merged);
+                Some(merged)
             } else {
-                return None;
+                None
             }
         }
+
+        /// Replaces the notes and bpm changes of this map with those of `converted`, keeping
+        /// its events, obstacles and bookmarks. With a `range`, only notes and bpm changes
+        /// within it are replaced, and the tempo at either end of it is kept in place.
+        pub fn update_from(&mut self, converted: &Root, range: Option<BeatRange>) {
+            let Some(range) = range else {
+                self.notes = converted.notes.clone();
+                self.custom_data.bpmchanges = converted.custom_data.bpmchanges.clone();
+                return;
+            };
+
+            self.notes.retain(|note| !range.contains(note.time));
+            self.notes.extend(
+                converted
+                    .notes
+                    .iter()
+                    .filter(|note| range.contains(note.time))
+                    .cloned(),
+            );
+            self.notes
+                .sort_by(|i, j| i.time.partial_cmp(&j.time).unwrap());
+
+            let existing = std::mem::take(&mut self.custom_data.bpmchanges);
+            let mut changes: Vec<Bpmchange> = existing
+                .iter()
+                .filter(|change| !range.contains(change.time))
+                .cloned()
+                .collect();
+            changes.extend(
+                converted
+                    .custom_data
+                    .bpmchanges
+                    .iter()
+                    .filter(|change| range.contains(change.time))
+                    .cloned(),
+            );
+            // The converted tempo applies from the start of the range, and the map's own tempo
+            // again from its end.
+            let boundaries = [
+                (range.start, &converted.custom_data.bpmchanges),
+                (range.end, &existing),
+            ];
+            for (beat, source) in boundaries {
+                if changes.iter().any(|change| change.time == beat) {
+                    continue;
+                }
+                let Some(change) = source.iter().rev().find(|change| change.time <= beat) else {
+                    continue;
+                };
+                let change = Bpmchange {
+                    time: beat,
+                    ..change.clone()
+                };
+                // Leave it out when the tempo before it is the same.
+                let previous = changes
+                    .iter()
+                    .filter(|previous| previous.time < beat)
+                    .max_by(|i, j| i.time.partial_cmp(&j.time).unwrap());
+                if previous.is_some_and(|previous| {
+                    previous.bpm == change.bpm
+                        && previous.beats_per_bar == change.beats_per_bar
+                        && previous.metronome_offset == change.metronome_offset
+                }) {
+                    continue;
+                }
+                changes.push(change);
+            }
+            changes.sort_by(|i, j| i.time.partial_cmp(&j.time).unwrap());
+            self.custom_data.bpmchanges = changes;
+        }
+
+        /// Converts a global beat into a 1-based bar and beat within that bar, using the
+        /// beats per bar of the bpm changes of this map. Only a change of meter starts a new
+        /// bar; a tempo change within a bar does not.
+        pub fn bar_and_beat(changes: &[Bpmchange], beat: f64) -> (u64, f64) {
+            let mut bars = 0_f64;
+            let mut bar_start = 0_f64;
+            let mut beats_per_bar = 4_f64;
+            for change in changes.iter() {
+                if change.time > beat {
+                    break;
+                }
+                let next = change.beats_per_bar.max(1) as f64;
+                if next != beats_per_bar {
+                    bars += ((change.time - bar_start) / beats_per_bar).ceil();
+                    bar_start = change.time;
+                    beats_per_bar = next;
+                }
+            }
+
+            let in_meter = beat - bar_start;
+            let bar = bars + (in_meter / beats_per_bar).floor();
+            (bar as u64 + 1, in_meter % beats_per_bar + 1_f64)
+        }
+
+        /// Converts a global beat into seconds using the bpm changes of this map.
+        pub fn seconds_at(&self, beat: f64) -> f64 {
+            let mut seconds = 0_f64;
+            let mut last_beat = 0_f64;
+            let mut last_bpm = 120_f64;
+            for change in self.custom_data.bpmchanges.iter() {
+                if change.time > beat {
+                    break;
+                }
+                seconds += (change.time - last_beat) * 60_f64 / last_bpm;
+                last_beat = change.time;
+                last_bpm = change.bpm;
+            }
+
+            seconds + (beat - last_beat) * 60_f64 / last_bpm
+        }
     }
 
     #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -196,20 +405,904 @@ pub mod edda_objects {
 }
 
 pub mod custom {
+    use std::{collections::BTreeMap, str::FromStr};
+
     use serde_derive::{Deserialize, Serialize};
+    use serde_json::{Map, Value};
+
+    use crate::error::TaikoError;
+
+    /// Song details written to info.dat. Not part of the saved config.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SongMetadata {
+        pub song_name: String,
+        pub song_author_name: String,
+        pub song_filename: String,
+        pub cover_image_filename: String,
+    }
+
+    impl Default for SongMetadata {
+        fn default() -> Self {
+            SongMetadata {
+                song_name: String::new(),
+                song_author_name: String::new(),
+                song_filename: "song.ogg".to_owned(),
+                cover_image_filename: "cover.jpg".to_owned(),
+            }
+        }
+    }
+
+    /// What to do with a note whose pitch is not in the drum map.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    pub enum UnmappedNotePolicy {
+        /// Stop the conversion with an error.
+        Error,
+        /// Leave the note out and report it.
+        #[default]
+        Skip,
+        /// Place the note in the lane with the closest pitch and report it.
+        Nearest,
+    }
+
+    impl std::fmt::Display for UnmappedNotePolicy {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Error => write!(f, "Error"),
+                Self::Skip => write!(f, "Skip"),
+                Self::Nearest => write!(f, "Nearest"),
+            }
+        }
+    }
+
+    /// How the tracks of a midi file are turned into difficulties.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    pub enum InputFormat {
+        /// Every note track becomes a difficulty named after the track.
+        #[default]
+        Tracks,
+        /// The `PART DRUMS` track of a Rock Band or Clone Hero chart is split into
+        /// difficulties by pitch range. Other tracks are ignored.
+        RockBand,
+    }
+
+    impl std::fmt::Display for InputFormat {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Tracks => write!(f, "Tracks"),
+                Self::RockBand => write!(f, "Rock Band"),
+            }
+        }
+    }
+
+    /// The osu!taiko hit a lane is exported as.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    pub enum OsuHit {
+        /// A red centre hit.
+        #[default]
+        Don,
+        /// A blue rim hit.
+        Kat,
+        BigDon,
+        BigKat,
+    }
+
+    impl OsuHit {
+        pub fn is_kat(&self) -> bool {
+            matches!(self, Self::Kat | Self::BigKat)
+        }
+
+        pub fn is_big(&self) -> bool {
+            matches!(self, Self::BigDon | Self::BigKat)
+        }
+    }
+
+    impl std::fmt::Display for OsuHit {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Don => write!(f, "Don"),
+                Self::Kat => write!(f, "Kat"),
+                Self::BigDon => write!(f, "Big Don"),
+                Self::BigKat => write!(f, "Big Kat"),
+            }
+        }
+    }
+
+    /// A time signature as written in a score, e.g. 6/8.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct TimeSignature {
+        pub numerator: u8,
+        /// Note value of a beat. Must be a power of two.
+        pub denominator: u8,
+    }
 
+    impl Default for TimeSignature {
+        /// The midi default of 4/4.
+        fn default() -> Self {
+            TimeSignature {
+                numerator: 4,
+                denominator: 4,
+            }
+        }
+    }
+
+    impl std::fmt::Display for TimeSignature {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+
+    impl std::str::FromStr for TimeSignature {
+        type Err = String;
+
+        /// Parses a time signature written as `numerator/denominator`.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let invalid = || format!("\"{}\" is not a time signature like 4/4 or 6/8", s);
+            let (numerator, denominator) = s.trim().split_once('/').ok_or_else(invalid)?;
+            let numerator: u8 = numerator.trim().parse().map_err(|_| invalid())?;
+            let denominator: u8 = denominator.trim().parse().map_err(|_| invalid())?;
+            if numerator == 0 || !denominator.is_power_of_two() || denominator > 64 {
+                return Err(invalid());
+            }
+
+            Ok(TimeSignature {
+                numerator,
+                denominator,
+            })
+        }
+    }
+
+    /// Beats from `start` up to, but not including, `end`, written `16-32`.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct BeatRange {
+        pub start: f64,
+        pub end: f64,
+    }
+
+    impl BeatRange {
+        pub fn contains(&self, beat: f64) -> bool {
+            beat >= self.start && beat < self.end
+        }
+    }
+
+    impl std::fmt::Display for BeatRange {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+
+    impl std::str::FromStr for BeatRange {
+        type Err = String;
+
+        /// Parses a range written as `start-end`, e.g. `16-32` or `8.5-12`.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let invalid = || format!("\"{}\" is not a beat range like 16-32", s);
+            let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+            let start: f64 = start.trim().parse().map_err(|_| invalid())?;
+            let end: f64 = end.trim().parse().map_err(|_| invalid())?;
+            if !start.is_finite() || !end.is_finite() || start < 0_f64 || start >= end {
+                return Err(invalid());
+            }
+
+            Ok(BeatRange { start, end })
+        }
+    }
+
+    /// A midi pitch or an inclusive range of pitches, written `38` or `"35-36"` in config.json.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(try_from = "PitchEntry", into = "PitchEntry")]
+    pub struct PitchRange {
+        pub low: u8,
+        pub high: u8,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum PitchEntry {
+        Pitch(u8),
+        Range(String),
+    }
+
+    impl PitchRange {
+        pub fn single(pitch: u8) -> Self {
+            PitchRange {
+                low: pitch,
+                high: pitch,
+            }
+        }
+
+        pub fn contains(&self, pitch: u8) -> bool {
+            (self.low..=self.high).contains(&pitch)
+        }
+
+        /// How many semitones `pitch` lies outside of the range.
+        pub fn distance(&self, pitch: u8) -> u8 {
+            if pitch < self.low {
+                self.low - pitch
+            } else {
+                pitch.saturating_sub(self.high)
+            }
+        }
+    }
+
+    impl std::fmt::Display for PitchRange {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            if self.low == self.high {
+                write!(f, "{}", self.low)
+            } else {
+                write!(f, "{}-{}", self.low, self.high)
+            }
+        }
+    }
+
+    impl std::str::FromStr for PitchRange {
+        type Err = String;
+
+        /// Parses a pitch like `38` or a range like `35-36`.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let invalid = || format!("\"{}\" is not a midi pitch or a range like 35-36", s);
+            let pitch = |p: &str| p.trim().parse::<u8>().ok().filter(|&p| p <= 127);
+            let range = match s.split_once('-') {
+                Some((low, high)) => PitchRange {
+                    low: pitch(low).ok_or_else(invalid)?,
+                    high: pitch(high).ok_or_else(invalid)?,
+                },
+                None => PitchRange::single(pitch(s).ok_or_else(invalid)?),
+            };
+            if range.low > range.high {
+                return Err(invalid());
+            }
+
+            Ok(range)
+        }
+    }
+
+    impl TryFrom<PitchEntry> for PitchRange {
+        type Error = String;
+
+        fn try_from(entry: PitchEntry) -> Result<Self, Self::Error> {
+            match entry {
+                PitchEntry::Pitch(pitch) => PitchRange::from_str(&pitch.to_string()),
+                PitchEntry::Range(range) => PitchRange::from_str(&range),
+            }
+        }
+    }
+
+    impl From<PitchRange> for PitchEntry {
+        fn from(range: PitchRange) -> Self {
+            if range.low == range.high {
+                PitchEntry::Pitch(range.low)
+            } else {
+                PitchEntry::Range(range.to_string())
+            }
+        }
+    }
+
+    /// The pitches played in one lane, e.g. `[38, 40, "35-36"]` for snare, rimshot and
+    /// cross-stick. A lane written as a single pitch, as in older configs, is read as a
+    /// list with that one pitch.
+    #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+    #[serde(from = "LaneEntry")]
+    pub struct LanePitches(pub Vec<PitchRange>);
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum LaneEntry {
+        Single(PitchRange),
+        List(Vec<PitchRange>),
+    }
+
+    impl From<LaneEntry> for LanePitches {
+        fn from(entry: LaneEntry) -> Self {
+            match entry {
+                LaneEntry::Single(range) => LanePitches(vec![range]),
+                LaneEntry::List(ranges) => LanePitches(ranges),
+            }
+        }
+    }
+
+    impl LanePitches {
+        /// Checks that the lane plays at least one pitch.
+        pub fn check(&self) -> Result<(), String> {
+            if self.0.is_empty() {
+                return Err("needs at least one pitch".to_owned());
+            }
+            Ok(())
+        }
+
+        pub fn contains(&self, pitch: u8) -> bool {
+            self.0.iter().any(|range| range.contains(pitch))
+        }
+
+        /// Semitones between `pitch` and the closest pitch of the lane.
+        pub fn distance(&self, pitch: u8) -> Option<u8> {
+            self.0.iter().map(|range| range.distance(pitch)).min()
+        }
+
+        /// The pitch written for this lane when converting back to midi.
+        pub fn first_pitch(&self) -> Option<u8> {
+            self.0.first().map(|range| range.low)
+        }
+    }
+
+    impl std::fmt::Display for LanePitches {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let ranges: Vec<String> = self.0.iter().map(|range| range.to_string()).collect();
+            write!(f, "{}", ranges.join(", "))
+        }
+    }
+
+    impl std::str::FromStr for LanePitches {
+        type Err = String;
+
+        /// Parses a comma separated list of pitches and ranges, e.g. `38, 40, 35-36`.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.split(',')
+                .map(str::trim)
+                .filter(|range| !range.is_empty())
+                .map(PitchRange::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .map(LanePitches)
+        }
+    }
+
+    /// Settings of one track that replace the general ones, e.g. for a track made from a
+    /// different DAW template.
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct TrackSettings {
+        /// Drum map used for the track instead of the general one.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub drum_map: Option<Vec<LanePitches>>,
+        /// Notes with a lower velocity are left out, e.g. ghost notes. 0 keeps every note.
+        pub min_velocity: u8,
+        /// Beats added to the time of every note, e.g. -0.25 moves notes a sixteenth earlier
+        /// in 4/4.
+        pub time_offset: f64,
+    }
+
+    /// A named drum map that can be applied in one go.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct DrumMapPreset {
+        pub name: String,
+        pub drum_map: Vec<LanePitches>,
+    }
+
+    impl DrumMapPreset {
+        fn new(name: &str, lanes: [&str; 4]) -> Self {
+            DrumMapPreset {
+                name: name.to_owned(),
+                drum_map: lanes
+                    .iter()
+                    .map(|lane| lane.parse().expect("built-in presets are valid"))
+                    .collect(),
+            }
+        }
+
+        /// Presets shipped with taiko for General MIDI and Rock Band drum tracks.
+        pub fn built_in() -> Vec<DrumMapPreset> {
+            vec![
+                // Kick, snare, toms and hi-hats, cymbals.
+                DrumMapPreset::new("GM 4-lane", ["35-36", "37-40", "41-48, 50", "49, 51-59"]),
+                // Kick and snare on the middle lanes, toms and cymbals outside, no hi-hats.
+                DrumMapPreset::new(
+                    "GM kick+snare focused",
+                    ["41, 43, 45, 47", "35-36", "37-40", "48-53, 55, 57, 59"],
+                ),
+                // Red, yellow, blue and green pads of every difficulty. Kicks are left out.
+                DrumMapPreset::new(
+                    "Rock Band pads",
+                    [
+                        "61, 73, 85, 97",
+                        "62, 74, 86, 98",
+                        "63, 75, 87, 99",
+                        "64, 76, 88, 100",
+                    ],
+                ),
+            ]
+        }
+    }
+
+    /// Missing settings take their default, so a config from an older version still loads.
     #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
     pub struct Config {
-        pub drum_map: Vec<u8>,
+        /// Pitches of each lane, indexed by lane. A pitch in several lanes goes to the first.
+        pub drum_map: Vec<LanePitches>,
         pub batch_output_extension: String,
+        /// File names the batch command converts, e.g. `*.mid`.
+        #[serde(default = "Config::default_batch_pattern")]
+        pub batch_pattern: String,
+        /// Previous versions kept of every .dat file taiko overwrites. 0 turns backups off.
+        #[serde(default = "Config::default_backup_generations")]
+        pub backup_generations: usize,
+        #[serde(default)]
+        pub unmapped_notes: UnmappedNotePolicy,
+        /// Number of decimal places beat times are rounded to.
+        #[serde(default = "Config::default_time_precision")]
+        pub time_precision: u32,
+        /// Tempo used for files without tempo events. Without one such files fail to convert.
+        #[serde(default)]
+        pub fallback_bpm: Option<f64>,
+        /// Time signature used for files without time signature events. Defaults to 4/4.
+        #[serde(default)]
+        pub fallback_time_signature: Option<TimeSignature>,
+        /// Drum maps saved by the user, listed after the built-in presets.
+        #[serde(default)]
+        pub drum_map_presets: Vec<DrumMapPreset>,
+        #[serde(default)]
+        pub input_format: InputFormat,
+        /// The osu!taiko hit of each lane, indexed by lane. Lanes past the end are dons.
+        #[serde(default = "Config::default_osu_hits")]
+        pub osu_hits: Vec<OsuHit>,
+        /// Settings for single tracks, by track name (or Rock Band difficulty).
+        #[serde(default)]
+        pub track_overrides: BTreeMap<String, TrackSettings>,
+        /// Settings this version of taiko does not know, kept so that saving does not drop
+        /// them.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    impl Config {
+        /// Lowest tempo accepted for the fallback bpm and `--bpm`, as in the gui.
+        pub const MIN_BPM: f64 = 1_f64;
+        /// Highest tempo accepted for the fallback bpm and `--bpm`, as in the gui.
+        pub const MAX_BPM: f64 = 999_f64;
+        /// Most decimal places beat times can be rounded to; an f64 holds about 15.
+        pub const MAX_TIME_PRECISION: u32 = 15;
+
+        fn default_time_precision() -> u32 {
+            6
+        }
+
+        fn default_batch_pattern() -> String {
+            "*.mid".to_owned()
+        }
+
+        fn default_backup_generations() -> usize {
+            10
+        }
+
+        /// Kats on the outer lanes and dons on the inner ones, like the rim and centre of a drum.
+        fn default_osu_hits() -> Vec<OsuHit> {
+            vec![OsuHit::Kat, OsuHit::Don, OsuHit::Don, OsuHit::Kat]
+        }
+
+        /// Built-in presets followed by the user's own.
+        pub fn presets(&self) -> Vec<DrumMapPreset> {
+            let mut presets = DrumMapPreset::built_in();
+            presets.extend(self.drum_map_presets.iter().cloned());
+            presets
+        }
+
+        /// Finds a preset by name, ignoring case. User presets shadow built-in ones.
+        pub fn preset(&self, name: &str) -> Option<DrumMapPreset> {
+            self.presets()
+                .into_iter()
+                .rev()
+                .find(|preset| preset.name.eq_ignore_ascii_case(name.trim()))
+        }
+
+        /// Saves the current drum map as a user preset, replacing one with the same name.
+        pub fn save_preset(&mut self, name: &str) {
+            let preset = DrumMapPreset {
+                name: name.trim().to_owned(),
+                drum_map: self.drum_map.clone(),
+            };
+            self.drum_map_presets
+                .retain(|saved| !saved.name.eq_ignore_ascii_case(&preset.name));
+            self.drum_map_presets.push(preset);
+        }
+
+        /// The first lane that plays `pitch`.
+        pub fn lane_for_pitch(&self, pitch: u8) -> Option<usize> {
+            self.drum_map.iter().position(|lane| lane.contains(pitch))
+        }
+
+        /// The settings of the track `name`, matched ignoring case and surrounding spaces.
+        pub fn track_settings(&self, name: &str) -> Option<&TrackSettings> {
+            self.track_overrides
+                .iter()
+                .find(|(track, _)| track.trim().eq_ignore_ascii_case(name.trim()))
+                .map(|(_, settings)| settings)
+        }
+
+        /// The drum map of the track `name`: its own, or the general one.
+        pub fn drum_map_for(&self, name: &str) -> &[LanePitches] {
+            self.track_settings(name)
+                .and_then(|settings| settings.drum_map.as_deref())
+                .unwrap_or(&self.drum_map)
+        }
+
+        /// Checks that `bpm` is a tempo the gui accepts.
+        pub fn check_bpm(bpm: f64) -> Result<f64, String> {
+            if (Config::MIN_BPM..=Config::MAX_BPM).contains(&bpm) {
+                Ok(bpm)
+            } else {
+                Err(format!(
+                    "{} is not a tempo between {} and {} bpm",
+                    bpm,
+                    Config::MIN_BPM,
+                    Config::MAX_BPM
+                ))
+            }
+        }
+
+        /// Checks the settings the way the gui does: every drum map lane plays a pitch, the
+        /// fallback bpm is within range and beat times are not rounded past what an f64
+        /// holds.
+        pub fn validate(&self) -> Result<(), String> {
+            let drum_maps = std::iter::once(("the drum map".to_owned(), &self.drum_map)).chain(
+                self.track_overrides.iter().filter_map(|(track, settings)| {
+                    let map = settings.drum_map.as_ref()?;
+                    Some((format!("the drum map of track {}", track), map))
+                }),
+            );
+            for (name, drum_map) in drum_maps {
+                for (i, lane) in drum_map.iter().enumerate() {
+                    lane.check()
+                        .map_err(|e| format!("lane {} of {} {}", i + 1, name, e))?;
+                }
+            }
+            if let Some(bpm) = self.fallback_bpm {
+                Config::check_bpm(bpm)?;
+            }
+            if self.time_precision > Config::MAX_TIME_PRECISION {
+                return Err(format!(
+                    "beat times can be rounded to at most {} decimal places",
+                    Config::MAX_TIME_PRECISION
+                ));
+            }
+            Ok(())
+        }
+
+        /// This config with the settings of `overrides`, a json object holding any of its
+        /// keys, in place of its own. Unknown keys are an error, so a typo is not ignored, and
+        /// the result is checked with [`Config::validate`].
+        pub fn with_overrides(&self, overrides: &Value) -> Result<Config, String> {
+            let Value::Object(overrides) = overrides else {
+                return Err("expected an object of settings".to_owned());
+            };
+            let Ok(Value::Object(mut settings)) = serde_json::to_value(self) else {
+                unreachable!("a config is written as a json object");
+            };
+            for (key, value) in overrides {
+                if !settings.contains_key(key) {
+                    return Err(format!("unknown setting \"{}\"", key));
+                }
+                settings.insert(key.clone(), value.clone());
+            }
+            let config: Config =
+                serde_json::from_value(Value::Object(settings)).map_err(|e| e.to_string())?;
+            config.validate()?;
+            Ok(config)
+        }
+
+        /// The settings as saved in config.json, by name.
+        fn settings(&self) -> Map<String, Value> {
+            let Ok(Value::Object(settings)) = serde_json::to_value(self) else {
+                unreachable!("a config is written as a json object");
+            };
+            settings
+        }
+
+        /// Names of the settings, sorted.
+        pub fn setting_names(&self) -> Vec<String> {
+            self.settings().keys().cloned().collect()
+        }
+
+        /// A setting as text, written the way [`Config::set_setting`] reads it: the drum map
+        /// as lanes separated by semicolons, e.g. `36; 38, 40; 45-47; 49`, a time signature
+        /// as `6/8`, text as it is, lists of text separated by commas and anything else as
+        /// json.
+        pub fn setting(&self, key: &str) -> Result<String, String> {
+            let value = self
+                .settings()
+                .remove(key)
+                .ok_or_else(|| format!("unknown setting \"{}\"", key))?;
+            Ok(match (key, value) {
+                ("drum_map", _) => {
+                    let lanes: Vec<String> =
+                        self.drum_map.iter().map(|lane| lane.to_string()).collect();
+                    lanes.join("; ")
+                }
+                ("fallback_time_signature", Value::Object(_)) => self
+                    .fallback_time_signature
+                    .map(|signature| signature.to_string())
+                    .unwrap_or_default(),
+                (_, Value::String(text)) => text,
+                (_, Value::Array(items))
+                    if !items.is_empty() && items.iter().all(|item| item.is_string()) =>
+                {
+                    let items: Vec<&str> = items.iter().filter_map(Value::as_str).collect();
+                    items.join(", ")
+                }
+                (_, value) => value.to_string(),
+            })
+        }
+
+        /// Sets a setting from text typed on the command line, checked like the gui does.
+        /// Drum map lanes are separated by commas, or by semicolons when a lane has several
+        /// pitches. `null` clears an optional setting, and lists like `osu_hits` are comma
+        /// separated, e.g. `Kat, Don, Don, Kat`. Anything else is read as json or as text.
+        pub fn set_setting(&mut self, key: &str, text: &str) -> Result<(), String> {
+            let current = self
+                .settings()
+                .remove(key)
+                .ok_or_else(|| format!("unknown setting \"{}\"", key))?;
+            let text = text.trim();
+            let json = || serde_json::from_str::<Value>(text);
+            let value = match (key, current) {
+                ("drum_map", _) => {
+                    let separator = if text.contains(';') { ';' } else { ',' };
+                    let lanes = text
+                        .split(separator)
+                        .map(LanePitches::from_str)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    serde_json::to_value(lanes).map_err(|e| e.to_string())?
+                }
+                ("fallback_time_signature", _) if text != "null" => {
+                    serde_json::to_value(TimeSignature::from_str(text)?)
+                        .map_err(|e| e.to_string())?
+                }
+                (_, Value::String(_)) => Value::String(text.to_owned()),
+                (_, Value::Array(_)) if json().is_err() => Value::Array(
+                    text.split(',')
+                        .map(|item| {
+                            let item = item.trim();
+                            serde_json::from_str(item)
+                                .unwrap_or_else(|_| Value::String(item.to_owned()))
+                        })
+                        .collect(),
+                ),
+                _ => json().unwrap_or_else(|_| Value::String(text.to_owned())),
+            };
+
+            let mut overrides = Map::new();
+            overrides.insert(key.to_owned(), value);
+            *self = self
+                .with_overrides(&Value::Object(overrides))
+                .map_err(|e| format!("invalid value for {}: {}", key, e))?;
+            Ok(())
+        }
     }
 
     impl Default for Config {
         fn default() -> Self {
             Config {
-                drum_map: (60..64).collect(),
+                drum_map: (60..64)
+                    .map(|pitch| LanePitches(vec![PitchRange::single(pitch)]))
+                    .collect(),
                 batch_output_extension: ".dat".to_owned(),
+                batch_pattern: Config::default_batch_pattern(),
+                backup_generations: Config::default_backup_generations(),
+                unmapped_notes: UnmappedNotePolicy::default(),
+                time_precision: Config::default_time_precision(),
+                fallback_bpm: None,
+                fallback_time_signature: None,
+                drum_map_presets: Vec::new(),
+                input_format: InputFormat::default(),
+                osu_hits: Config::default_osu_hits(),
+                track_overrides: BTreeMap::new(),
+                extra: Map::new(),
+            }
+        }
+    }
+
+    /// A configuration saved under a name.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Profile {
+        pub name: String,
+        #[serde(flatten)]
+        pub config: Config,
+    }
+
+    /// The profiles saved in config.json and the name of the one in use. Files of older
+    /// versions are migrated when read.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(try_from = "Value")]
+    pub struct ProfileStore {
+        /// Version of the config.json layout, [`ProfileStore::VERSION`] once read.
+        pub version: u64,
+        pub active_profile: String,
+        pub profiles: Vec<Profile>,
+        /// Settings this version of taiko does not know, kept so that saving does not drop
+        /// them.
+        #[serde(flatten)]
+        pub extra: Map<String, Value>,
+    }
+
+    /// The layout of [`ProfileStore`], read after migrating.
+    #[derive(Deserialize)]
+    struct ProfileStoreFile {
+        active_profile: String,
+        profiles: Vec<Profile>,
+        #[serde(flatten)]
+        extra: Map<String, Value>,
+    }
+
+    /// Upgrades a config.json of one version to the next. The migration at index `i` turns
+    /// version `i` into version `i + 1`.
+    const MIGRATIONS: [fn(Value) -> Result<Value, String>; 1] = [profiles_from_single_config];
+
+    /// Version 0 held a single configuration. It becomes the only profile of version 1.
+    fn profiles_from_single_config(config: Value) -> Result<Value, String> {
+        let Value::Object(mut profile) = config else {
+            return Err("expected an object of settings".to_owned());
+        };
+        profile.insert("name".to_owned(), ProfileStore::DEFAULT_PROFILE.into());
+        Ok(serde_json::json!({
+            "active_profile": ProfileStore::DEFAULT_PROFILE,
+            "profiles": [profile],
+        }))
+    }
+
+    /// Migrates the contents of a config.json of any earlier version to the current one.
+    pub fn migrate_config(mut config: Value) -> Result<Value, String> {
+        // Files from before versioning are told apart by their layout.
+        let version = match config.get("version") {
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| format!("version {} is not a whole number", version))?,
+            None if config.get("profiles").is_some() => 1,
+            None => 0,
+        };
+        if version > ProfileStore::VERSION {
+            return Err(format!(
+                "it was saved by a newer version of taiko (config version {}, this version reads up to {})",
+                version,
+                ProfileStore::VERSION
+            ));
+        }
+
+        for migration in &MIGRATIONS[version as usize..] {
+            config = migration(config)?;
+        }
+        if let Value::Object(settings) = &mut config {
+            settings.insert("version".to_owned(), ProfileStore::VERSION.into());
+        }
+        Ok(config)
+    }
+
+    impl TryFrom<Value> for ProfileStore {
+        type Error = String;
+
+        fn try_from(config: Value) -> Result<Self, Self::Error> {
+            let mut file: ProfileStoreFile =
+                serde_json::from_value(migrate_config(config)?).map_err(|e| e.to_string())?;
+            file.extra.remove("version");
+            if file.profiles.is_empty() {
+                return Ok(ProfileStore {
+                    extra: file.extra,
+                    ..Default::default()
+                });
+            }
+
+            let mut store = ProfileStore {
+                version: ProfileStore::VERSION,
+                active_profile: file.active_profile,
+                profiles: file.profiles,
+                extra: file.extra,
+            };
+            if store.profile(&store.active_profile).is_none() {
+                store.active_profile = store.profiles[0].name.clone();
             }
+            Ok(store)
+        }
+    }
+
+    impl ProfileStore {
+        /// Name of the profile made from the configuration of an older config.json.
+        pub const DEFAULT_PROFILE: &'static str = "Default";
+
+        /// Version of the config.json layout this version of taiko writes. Raise it with a
+        /// new entry in `MIGRATIONS` whenever a change would break reading older files.
+        pub const VERSION: u64 = MIGRATIONS.len() as u64;
+
+        /// A store with `config` as its only profile.
+        pub fn from_config(config: Config) -> Self {
+            ProfileStore {
+                version: ProfileStore::VERSION,
+                active_profile: ProfileStore::DEFAULT_PROFILE.to_owned(),
+                profiles: vec![Profile {
+                    name: ProfileStore::DEFAULT_PROFILE.to_owned(),
+                    config,
+                }],
+                extra: Map::new(),
+            }
+        }
+
+        /// Finds a profile by name, ignoring case.
+        pub fn profile(&self, name: &str) -> Option<&Profile> {
+            self.profiles
+                .iter()
+                .find(|profile| profile.name.eq_ignore_ascii_case(name.trim()))
+        }
+
+        /// The configuration of the profile in use.
+        pub fn active(&self) -> &Config {
+            &self
+                .profile(&self.active_profile)
+                .unwrap_or(&self.profiles[0])
+                .config
+        }
+
+        /// Makes `name` the profile in use.
+        pub fn select(&mut self, name: &str) -> Result<(), TaikoError> {
+            let profile = self.existing(name)?;
+            self.active_profile = profile.name.clone();
+            Ok(())
+        }
+
+        /// Replaces the configuration of the profile `name`.
+        pub fn set_config(&mut self, name: &str, config: Config) -> Result<(), TaikoError> {
+            let i = self.position(name)?;
+            self.profiles[i].config = config;
+            Ok(())
+        }
+
+        /// Adds a profile with `config`. Names are unique, ignoring case.
+        pub fn create(&mut self, name: &str, config: Config) -> Result<(), TaikoError> {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(TaikoError::ProfileConflict(
+                    "A profile needs a name".to_owned(),
+                ));
+            }
+            if self.profile(name).is_some() {
+                return Err(TaikoError::ProfileConflict(format!(
+                    "A profile named \"{}\" already exists",
+                    name
+                )));
+            }
+
+            self.profiles.push(Profile {
+                name: name.to_owned(),
+                config,
+            });
+            Ok(())
+        }
+
+        /// Adds a copy of the profile `from` named `to`.
+        pub fn copy(&mut self, from: &str, to: &str) -> Result<(), TaikoError> {
+            let config = self.existing(from)?.config.clone();
+            self.create(to, config)
+        }
+
+        /// Removes a profile. When it was in use, the first remaining profile takes over.
+        /// The last profile cannot be deleted.
+        pub fn delete(&mut self, name: &str) -> Result<(), TaikoError> {
+            let i = self.position(name)?;
+            if self.profiles.len() == 1 {
+                return Err(TaikoError::ProfileConflict(format!(
+                    "\"{}\" is the only profile and cannot be deleted",
+                    self.profiles[i].name
+                )));
+            }
+
+            let removed = self.profiles.remove(i);
+            if removed.name.eq_ignore_ascii_case(&self.active_profile) {
+                self.active_profile = self.profiles[0].name.clone();
+            }
+            Ok(())
+        }
+
+        fn existing(&self, name: &str) -> Result<&Profile, TaikoError> {
+            self.profile(name)
+                .ok_or_else(|| TaikoError::UnknownProfile(name.trim().to_owned()))
+        }
+
+        fn position(&self, name: &str) -> Result<usize, TaikoError> {
+            self.profiles
+                .iter()
+                .position(|profile| profile.name.eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| TaikoError::UnknownProfile(name.trim().to_owned()))
+        }
+    }
+
+    impl Default for ProfileStore {
+        fn default() -> Self {
+            ProfileStore::from_config(Config::default())
         }
     }
 }
@@ -0,0 +1,22 @@
+//! Conversion between midi files and Ragnarock/Edda beatmaps, with export to osu!taiko
+//! and StepMania. REAPER projects can be read in place of midi files.
+//!
+//! The `taiko` binary is a thin cli and gui front-end over this library.
+
+pub mod backup;
+pub mod batch;
+pub mod converters;
+pub mod error;
+pub mod file_io;
+pub mod json_structures;
+pub mod osu;
+pub mod package;
+pub mod reaper;
+pub mod stepmania;
+pub mod watch;
+
+pub use converters::{
+    Conversion, ConversionReport, DatConverter, MidiConversion, MidiConverter, SkippedNote,
+};
+pub use error::TaikoError;
+pub use osu::OsuBeatmap;
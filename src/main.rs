@@ -1,17 +1,26 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+use std::{path::PathBuf, time::SystemTime};
+
 use clap::Parser;
 use clap::Subcommand;
+use taiko::json_structures::custom::{BeatRange, Config, TimeSignature};
 use taiko_app::TaikoApp;
 
-mod converters;
-mod file_io;
-mod json_structures;
 mod taiko_app;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    /// Drum map preset to use instead of the configured drum map, e.g. "GM 4-lane".
+    #[arg(long, global = true)]
+    preset: Option<String>,
+    /// Configuration profile to use instead of the one selected in config.json.
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Config file to use instead of the global one. taiko.json project files are ignored.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -19,24 +28,221 @@ pub struct Args {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Takes a midi file and outputs the conversion to the given path.
-    Convert { source: String, output_file: String },
+    Convert {
+        source: String,
+        output_file: String,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        input: InputArgs,
+    },
     /// Takes a midi file and outputs multiple data files based off of track names.
     Auto {
         source: String,
         output_folder: String,
+        #[command(flatten)]
+        song: SongArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Converts every matching midi file below a folder like auto, into the folder next to it.
+    Batch {
+        root_folder: String,
+        /// File names to convert, e.g. "*.mid". Defaults to batch_pattern in config.json.
+        #[arg(long)]
+        pattern: Option<String>,
+        /// Number of songs converted at the same time. Defaults to the number of cpus.
+        #[arg(long)]
+        jobs: Option<usize>,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Converts a midi file like auto, then again every time the file changes.
+    Watch {
+        source: String,
+        output_folder: String,
+        /// Milliseconds the file must stay unchanged before it is converted again.
+        #[arg(long, default_value_t = 500)]
+        debounce: u64,
+        #[command(flatten)]
+        song: SongArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Builds a ready-to-play song folder (or zip) with info.dat, difficulties, audio and cover.
+    Package {
+        source: String,
+        output_folder: String,
+        /// Audio file to copy into the song folder.
+        #[arg(long)]
+        audio: PathBuf,
+        /// Cover image to copy into the song folder.
+        #[arg(long)]
+        cover: PathBuf,
+        /// Song name. Defaults to the source file name.
+        #[arg(long)]
+        song_name: Option<String>,
+        /// Song author.
+        #[arg(long, default_value = "")]
+        author: String,
+        /// Write a zip archive instead of a folder.
+        #[arg(long)]
+        zip: bool,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Takes a midi file and writes an osu!taiko .osu file for every difficulty.
+    Osu {
+        source: String,
+        output_folder: String,
+        #[command(flatten)]
+        song: SongArgs,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Takes a midi file and writes a StepMania .sm file with every difficulty.
+    Sm {
+        source: String,
+        output_folder: String,
+        #[command(flatten)]
+        song: SongArgs,
+        #[command(flatten)]
+        input: InputArgs,
+    },
+    /// Takes a StepMania .sm or .ssc file and writes its dance-single charts and an info.dat.
+    Sm2dat {
+        source: String,
+        output_folder: String,
+    },
+    /// Takes an Edda .dat file and converts it back into a midi file.
+    Dat2midi { source: String, output_file: String },
+    /// Rolls a .dat file back to one of the backups taken before it was overwritten.
+    Restore {
+        file: PathBuf,
+        /// Backup to restore, counting from 1 for the newest, as numbered by --list.
+        #[arg(default_value_t = 1)]
+        number: usize,
+        /// List the file's backups instead of restoring one.
+        #[arg(long)]
+        list: bool,
     },
     /// Lets the user configure taiko
     Configure,
+    /// Saves the drum map (or the one picked with --preset) as a named preset.
+    SavePreset { name: String },
+    /// Lists the built-in and saved drum map presets.
+    Presets,
+    /// Manages the configuration profiles saved in config.json.
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+    /// Reads and edits the settings of the profile in use, and shows where they are read from.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
 }
 
-fn main() {
-    let config = TaikoApp::get_or_create_config();
-    let app = TaikoApp::from_config(config);
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+    /// Lists the config files merged for a source, in the order they are applied.
+    Path {
+        /// Source file or folder to find a taiko.json for. Defaults to the working directory.
+        source: Option<PathBuf>,
+    },
+    /// Prints one setting, e.g. drum_map.
+    Get { key: String },
+    /// Changes one setting, e.g. `set drum_map 36,38,45,49` or `set fallback_bpm null`.
+    Set { key: String, value: String },
+    /// Restores the default of one setting, or of every setting.
+    Reset { key: Option<String> },
+    /// Prints every setting.
+    Show {
+        /// Print the settings as they are saved in config.json.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ProfileCommands {
+    /// Lists the profiles. The one in use is marked with *.
+    List,
+    /// Creates a profile with the default configuration.
+    Create { name: String },
+    /// Deletes a profile.
+    Delete { name: String },
+    /// Copies a profile under a new name.
+    Copy { from: String, to: String },
+}
 
+/// Song details written to the generated info.dat.
+#[derive(clap::Args, Debug)]
+pub struct SongArgs {
+    /// Song name. Defaults to the source file name.
+    #[arg(long)]
+    song_name: Option<String>,
+    /// Song author.
+    #[arg(long, default_value = "")]
+    author: String,
+    /// Audio file name referenced by info.dat.
+    #[arg(long, default_value = "song.ogg")]
+    song_file: String,
+    /// Cover image file name referenced by info.dat.
+    #[arg(long, default_value = "cover.jpg")]
+    cover_file: String,
+}
+
+/// How .dat files that already exist are written.
+#[derive(clap::Args, Debug)]
+pub struct OutputArgs {
+    /// Keep the events, obstacles and bookmarks of existing .dat files and replace only their
+    /// notes and bpm changes.
+    #[arg(long)]
+    update: bool,
+    /// With --update, only replace the notes and bpm changes within these beats, e.g. 16-32.
+    #[arg(long, requires = "update")]
+    beats: Option<BeatRange>,
+    /// Set by Watch to the start of its session, so each file is backed up only once.
+    #[arg(skip)]
+    backup_since: Option<SystemTime>,
+}
+
+/// How the source midi file is read.
+#[derive(clap::Args, Debug)]
+pub struct InputArgs {
+    /// Read the PART DRUMS track of a Rock Band or Clone Hero chart and split it into
+    /// difficulties by pitch range.
+    #[arg(long)]
+    rock_band: bool,
+    /// Tempo used when the midi file has no tempo events, e.g. 120 for the midi default.
+    #[arg(long, value_parser = parse_bpm)]
+    bpm: Option<f64>,
+    /// Time signature used when the midi file has none, e.g. 6/8. Defaults to 4/4.
+    #[arg(long)]
+    time_signature: Option<TimeSignature>,
+}
+
+/// Accepts the tempos the GUI allows, see [`Config::check_bpm`].
+fn parse_bpm(text: &str) -> Result<f64, String> {
+    let bpm: f64 = text
+        .parse()
+        .map_err(|_| format!("{} is not a number", text))?;
+    Config::check_bpm(bpm)
+}
+
+fn main() {
     let args: Vec<_> = std::env::args().collect();
     if args.len() > 1 {
-        app.run_cli()
+        TaikoApp::run_cli()
     } else {
-        app.run_gui()
+        TaikoApp::run_gui()
     }
 }
@@ -0,0 +1,134 @@
+use std::fmt::Write;
+
+use crate::json_structures::{
+    custom::{Config, OsuHit, SongMetadata},
+    edda_objects::Root,
+};
+
+/// An osu!taiko beatmap built from one converted difficulty.
+///
+/// Notes that land on the same millisecond become a single hit, since osu!taiko cannot
+/// play two at once. The lowest lane decides between don and kat, and the hit is big when
+/// any of the lanes is big or when two of them share the colour.
+pub struct OsuBeatmap<'a> {
+    song: &'a SongMetadata,
+    difficulty: &'a str,
+    root: &'a Root,
+    hits: Vec<OsuHit>,
+}
+
+impl<'a> OsuBeatmap<'a> {
+    /// osu! hitsound flags used for kats and big notes. A hit without flags is a don.
+    const HITSOUND_FINISH: u8 = 4;
+    const HITSOUND_CLAP: u8 = 8;
+
+    pub fn new(song: &'a SongMetadata, difficulty: &'a str, root: &'a Root) -> Self {
+        OsuBeatmap {
+            song,
+            difficulty,
+            root,
+            hits: Config::default().osu_hits,
+        }
+    }
+
+    pub fn with_config(mut self, configuration: &Config) -> Self {
+        self.hits = configuration.osu_hits.clone();
+        self
+    }
+
+    /// File name osu! uses for the difficulty, e.g. `Artist - Title (taiko) [Hard].osu`.
+    pub fn file_name(&self) -> String {
+        let name = format!(
+            "{} - {} (taiko) [{}].osu",
+            self.song.song_author_name, self.song.song_name, self.difficulty
+        );
+        name.chars()
+            .filter(|c| !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+            .collect()
+    }
+
+    /// Renders the beatmap in the .osu file format.
+    pub fn to_osu_string(&self) -> String {
+        let mut osu = String::new();
+        let song = self.song;
+
+        osu.push_str("osu file format v14\n\n[General]\n");
+        let _ = writeln!(osu, "AudioFilename: {}", song.song_filename);
+        osu.push_str("AudioLeadIn: 0\nPreviewTime: -1\nMode: 1\n\n[Metadata]\n");
+        let _ = writeln!(osu, "Title:{}", song.song_name);
+        let _ = writeln!(osu, "Artist:{}", song.song_author_name);
+        let _ = writeln!(osu, "Creator:taiko\nVersion:{}", self.difficulty);
+        osu.push_str(
+            "\n[Difficulty]\nHPDrainRate:5\nCircleSize:5\nOverallDifficulty:5\nApproachRate:5\nSliderMultiplier:1.4\nSliderTickRate:1\n\n[Events]\n",
+        );
+        let _ = writeln!(osu, "0,0,\"{}\",0,0", song.cover_image_filename);
+
+        osu.push_str("\n[TimingPoints]\n");
+        for change in self.root.custom_data.bpmchanges.iter() {
+            // time,beatLength,meter,sampleSet,sampleIndex,volume,uninherited,effects
+            let _ = writeln!(
+                osu,
+                "{},{},{},1,0,100,1,0",
+                self.milliseconds_at(change.time),
+                60_000_f64 / change.bpm,
+                change.beats_per_bar.max(1)
+            );
+        }
+
+        osu.push_str("\n[HitObjects]\n");
+        for (time, hitsound) in self.hit_objects() {
+            // x,y,time,type,hitSound,hitSample
+            let _ = writeln!(osu, "256,192,{},1,{},0:0:0:0:", time, hitsound);
+        }
+
+        osu
+    }
+
+    fn milliseconds_at(&self, beat: f64) -> i64 {
+        (self.root.seconds_at(beat) * 1000_f64).round() as i64
+    }
+
+    fn hit_for_lane(&self, lane: i64) -> OsuHit {
+        usize::try_from(lane)
+            .ok()
+            .and_then(|lane| self.hits.get(lane))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Time and hitsound of every hit, with simultaneous notes merged.
+    fn hit_objects(&self) -> Vec<(i64, u8)> {
+        let mut notes: Vec<(i64, i64)> = self
+            .root
+            .notes
+            .iter()
+            .map(|note| (self.milliseconds_at(note.time), note.line_index))
+            .collect();
+        notes.sort_unstable();
+
+        let mut objects = Vec::<(i64, u8)>::new();
+        for chord in notes.chunk_by(|a, b| a.0 == b.0) {
+            let hits: Vec<OsuHit> = chord
+                .iter()
+                .map(|&(_, lane)| self.hit_for_lane(lane))
+                .collect();
+            let first = hits[0];
+            let same_colour = hits
+                .iter()
+                .filter(|hit| hit.is_kat() == first.is_kat())
+                .count();
+            let big = hits.iter().any(OsuHit::is_big) || same_colour > 1;
+
+            let mut hitsound = 0;
+            if first.is_kat() {
+                hitsound |= OsuBeatmap::HITSOUND_CLAP;
+            }
+            if big {
+                hitsound |= OsuBeatmap::HITSOUND_FINISH;
+            }
+            objects.push((chord[0].0, hitsound));
+        }
+
+        objects
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::{
+    backup,
+    error::TaikoError,
+    json_structures::{custom::SongMetadata, edda_info, edda_objects},
+};
+
+/// Everything needed to build a playable Ragnarock custom song.
+pub struct SongPackage {
+    pub info: edda_info::Root,
+    pub beatmaps: Vec<(String, edda_objects::Root)>,
+    pub extension: String,
+    pub audio: PathBuf,
+    pub cover: PathBuf,
+    /// Previous versions kept of beatmap files the package overwrites, see [`crate::backup`].
+    pub backup_generations: usize,
+}
+
+impl SongPackage {
+    /// Creates a package whose info.dat references the picked audio and cover files by name.
+    pub fn new(
+        song: &SongMetadata,
+        beatmaps: Vec<(String, edda_objects::Root)>,
+        extension: &str,
+        audio: PathBuf,
+        cover: PathBuf,
+    ) -> Self {
+        let mut song = song.clone();
+        song.song_filename = file_name(&audio);
+        song.cover_image_filename = file_name(&cover);
+
+        SongPackage {
+            info: edda_info::Root::from_difficulties(&song, &beatmaps, extension),
+            beatmaps,
+            extension: extension.to_owned(),
+            audio,
+            cover,
+            backup_generations: 0,
+        }
+    }
+
+    /// Backs up beatmap files and info.dat before a folder write overwrites them.
+    pub fn with_backups(mut self, generations: usize) -> Self {
+        self.backup_generations = generations;
+        self
+    }
+
+    /// Folder name used for the song. Ragnarock expects lowercase names without spaces.
+    pub fn folder_name(&self) -> String {
+        let name: String = self
+            .info
+            .song_name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        if name.is_empty() {
+            "customsong".to_string()
+        } else {
+            name
+        }
+    }
+
+    /// Checks that the audio and cover files exist, that the audio is an .ogg file, which
+    /// is the only format Ragnarock plays, and that the file names in info.dat match the
+    /// files that will be written.
+    pub fn validate(&self) -> Result<(), TaikoError> {
+        for path in [&self.audio, &self.cover] {
+            if !path.is_file() {
+                return Err(TaikoError::Io {
+                    path: path.clone(),
+                    source: io::Error::new(io::ErrorKind::NotFound, "file does not exist"),
+                });
+            }
+        }
+
+        let is_ogg = self
+            .audio
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("ogg"));
+        if !is_ogg {
+            return Err(TaikoError::InvalidPackage(format!(
+                "the audio file {} is not an .ogg file",
+                self.audio.display()
+            )));
+        }
+
+        if self.info.song_filename != file_name(&self.audio) {
+            return Err(mismatch("_songFilename", &self.info.song_filename));
+        }
+        if self.info.cover_image_filename != file_name(&self.cover) {
+            return Err(mismatch(
+                "_coverImageFilename",
+                &self.info.cover_image_filename,
+            ));
+        }
+
+        let written: Vec<String> = self
+            .beatmaps
+            .iter()
+            .map(|(name, _)| format!("{}{}", name, self.extension))
+            .collect();
+        for set in self.info.difficulty_beatmap_sets.iter() {
+            for beatmap in set.difficulty_beatmaps.iter() {
+                if !written.contains(&beatmap.beatmap_filename) {
+                    return Err(mismatch("_beatmapFilename", &beatmap.beatmap_filename));
+                }
+            }
+        }
+
+        if written.is_empty() {
+            return Err(TaikoError::EmptyTrack);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the song folder into `destination` and returns its path.
+    pub fn write_folder(&self, destination: &Path) -> Result<PathBuf, TaikoError> {
+        self.validate()?;
+
+        let folder = destination.join(self.folder_name());
+        fs::create_dir_all(&folder).map_err(TaikoError::io(&folder))?;
+
+        for (name, data) in self.files()? {
+            let path = folder.join(&name);
+            if name == "info.dat" || name.ends_with(&self.extension) {
+                backup::back_up(&path, self.backup_generations)?;
+            }
+            File::create(&path)
+                .and_then(|mut file| file.write_all(&data))
+                .map_err(TaikoError::io(&path))?;
+        }
+
+        Ok(folder)
+    }
+
+    /// Writes the song folder as a zip archive into `destination` and returns its path.
+    pub fn write_zip(&self, destination: &Path) -> Result<PathBuf, TaikoError> {
+        self.validate()?;
+
+        let folder_name = self.folder_name();
+        let zip_path = destination.join(format!("{}.zip", folder_name));
+
+        let files = self.files()?;
+        let write = || -> Result<(), io::Error> {
+            let mut zip = ZipWriter::new(File::create(&zip_path)?);
+            let options = FileOptions::default();
+            for (name, data) in files {
+                zip.start_file(format!("{}/{}", folder_name, name), options)?;
+                zip.write_all(&data)?;
+            }
+            zip.finish()?;
+            Ok(())
+        };
+        write().map_err(TaikoError::io(&zip_path))?;
+
+        Ok(zip_path)
+    }
+
+    fn files(&self) -> Result<Vec<(String, Vec<u8>)>, TaikoError> {
+        let mut files = Vec::<(String, Vec<u8>)>::new();
+        files.push((
+            "info.dat".to_string(),
+            serde_json::to_vec_pretty(&self.info)?,
+        ));
+        for (name, root) in self.beatmaps.iter() {
+            files.push((
+                format!("{}{}", name, self.extension),
+                serde_json::to_vec_pretty(root)?,
+            ));
+        }
+        for path in [&self.audio, &self.cover] {
+            files.push((
+                file_name(path),
+                fs::read(path).map_err(TaikoError::io(path))?,
+            ));
+        }
+
+        Ok(files)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn mismatch(field: &str, value: &str) -> TaikoError {
+    TaikoError::InvalidPackage(format!(
+        "info.dat {} \"{}\" does not match a packaged file",
+        field, value
+    ))
+}
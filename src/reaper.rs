@@ -0,0 +1,460 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use midly::{
+    num::{u15, u24, u4, u7},
+    Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEventKind,
+};
+
+use crate::{converters::DatConverter, error::TaikoError};
+
+/// A REAPER project read as a midi source.
+///
+/// The project tempo envelope becomes tempo and time signature events, and the midi items
+/// of every track, in-project or referenced `.mid` files, become a note track named after
+/// the REAPER track. Items are placed on the beat grid by their position, and notes outside
+/// of an item's length are left out, as REAPER would not play them. Gradual tempo ramps are
+/// read as steps, and muted items and notes are skipped.
+///
+/// ```no_run
+/// # fn main() -> Result<(), taiko::TaikoError> {
+/// let midi = taiko::reaper::RppProject::from_file("song.rpp")?.to_midi()?;
+/// let conversion = taiko::MidiConverter::from_bytes(midi).convert()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RppProject<'a> {
+    text: Cow<'a, str>,
+    /// Folder that referenced midi files are relative to.
+    folder: PathBuf,
+}
+
+/// A `<NAME params` block of a project file with its lines and nested blocks in order.
+struct Chunk {
+    name: String,
+    params: Vec<String>,
+    entries: Vec<Entry>,
+}
+
+enum Entry {
+    Line(Vec<String>),
+    Chunk(Chunk),
+}
+
+impl Chunk {
+    fn lines(&self) -> impl Iterator<Item = &Vec<String>> {
+        self.entries.iter().filter_map(|entry| match entry {
+            Entry::Line(line) => Some(line),
+            Entry::Chunk(_) => None,
+        })
+    }
+
+    fn children<'c>(&'c self, name: &'c str) -> impl Iterator<Item = &'c Chunk> {
+        self.entries.iter().filter_map(move |entry| match entry {
+            Entry::Chunk(chunk) if chunk.name == name => Some(chunk),
+            _ => None,
+        })
+    }
+
+    /// The parameters of the first line starting with `key`.
+    fn value(&self, key: &str) -> Option<&[String]> {
+        self.lines()
+            .find(|line| line.first().is_some_and(|first| first == key))
+            .map(|line| &line[1..])
+    }
+
+    fn number(&self, key: &str) -> Option<f64> {
+        self.value(key)?.first()?.parse().ok()
+    }
+}
+
+/// Tempo of the project from a point in seconds on, with its beat and time signature.
+struct TempoPoint {
+    seconds: f64,
+    beat: f64,
+    bpm: f64,
+    time_signature: Option<(u8, u8)>,
+}
+
+impl<'a> RppProject<'a> {
+    /// Ticks per quarter note of the generated midi file.
+    const PPQ: u16 = 960;
+    /// Ticks per quarter note REAPER uses for in-project midi when none is given.
+    const DEFAULT_SOURCE_PPQ: f64 = 960_f64;
+
+    /// Creates a project from the text of a .rpp file. Referenced midi files are looked
+    /// up relative to the working directory.
+    pub fn from_text(text: impl Into<Cow<'a, str>>) -> Self {
+        RppProject {
+            text: text.into(),
+            folder: PathBuf::new(),
+        }
+    }
+
+    /// Reads the .rpp file at `path`. Referenced midi files are looked up next to it.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TaikoError> {
+        let text = std::fs::read_to_string(path.as_ref()).map_err(TaikoError::io(&path))?;
+        let mut project = RppProject::from_text(text);
+        project.folder = path
+            .as_ref()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        Ok(project)
+    }
+
+    /// Writes the project's tempo map and midi items as a standard midi file.
+    pub fn to_midi(&self) -> Result<Vec<u8>, TaikoError> {
+        let project = RppProject::parse(&self.text)?;
+        if project.name != "REAPER_PROJECT" {
+            return Err(TaikoError::RppParse(
+                "the file is not a REAPER project".to_string(),
+            ));
+        }
+
+        let tempo = RppProject::tempo_points(&project)?;
+        let ppq = RppProject::PPQ as f64;
+        let to_ticks = |beat: f64| (beat * ppq).round().max(0_f64) as u64;
+
+        let mut meta = Vec::<(u64, TrackEventKind)>::new();
+        for point in tempo.iter() {
+            let ticks = to_ticks(point.beat);
+            let tempo = (60_000_000_f64 / point.bpm).round() as u32;
+            meta.push((
+                ticks,
+                TrackEventKind::Meta(MetaMessage::Tempo(u24::new(tempo))),
+            ));
+            if let Some((numerator, denominator)) = point.time_signature {
+                meta.push((
+                    ticks,
+                    TrackEventKind::Meta(MetaMessage::TimeSignature(
+                        numerator,
+                        denominator.trailing_zeros() as u8,
+                        24,
+                        8,
+                    )),
+                ));
+            }
+        }
+
+        let mut names = Vec::<String>::new();
+        let mut tracks = Vec::<Vec<(u64, u8, u8, u8)>>::new();
+        for track in project.children("TRACK") {
+            let mut notes = Vec::<(u64, u8, u8, u8)>::new();
+            for item in track.children("ITEM") {
+                if item.number("MUTE").unwrap_or_default() != 0_f64 {
+                    continue;
+                }
+                for (beat, channel, key, velocity) in self.item_notes(item, &tempo)? {
+                    notes.push((to_ticks(beat), channel, key, velocity));
+                }
+            }
+            if notes.is_empty() {
+                continue;
+            }
+            let name = track
+                .value("NAME")
+                .and_then(|name| name.first())
+                .cloned()
+                .unwrap_or_default();
+            names.push(name);
+            tracks.push(notes);
+        }
+
+        let mut smf = Smf {
+            header: Header::new(
+                Format::Parallel,
+                Timing::Metrical(u15::new(RppProject::PPQ)),
+            ),
+            tracks: vec![DatConverter::to_track(meta)],
+        };
+        for (name, notes) in names.iter().zip(tracks) {
+            let mut events = Vec::<(u64, TrackEventKind)>::new();
+            // Unnamed tracks keep the converter's naming by track order.
+            if !name.is_empty() {
+                events.push((
+                    0,
+                    TrackEventKind::Meta(MetaMessage::TrackName(name.as_bytes())),
+                ));
+            }
+            for (ticks, channel, key, velocity) in notes {
+                let channel = u4::new(channel);
+                let key = u7::new(key);
+                events.push((
+                    ticks + 1,
+                    TrackEventKind::Midi {
+                        channel,
+                        message: MidiMessage::NoteOff {
+                            key,
+                            vel: u7::new(0),
+                        },
+                    },
+                ));
+                events.push((
+                    ticks,
+                    TrackEventKind::Midi {
+                        channel,
+                        message: MidiMessage::NoteOn {
+                            key,
+                            vel: u7::new(velocity),
+                        },
+                    },
+                ));
+            }
+            smf.tracks.push(DatConverter::to_track(events));
+        }
+
+        let mut bytes = Vec::<u8>::new();
+        smf.write(&mut bytes).map_err(TaikoError::MidiWrite)?;
+        Ok(bytes)
+    }
+
+    /// Splits the project into nested chunks.
+    fn parse(text: &str) -> Result<Chunk, TaikoError> {
+        let mut stack = Vec::<Chunk>::new();
+        let mut root = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(header) = line.strip_prefix('<') {
+                let mut tokens = tokenize(header);
+                let name = if tokens.is_empty() {
+                    String::new()
+                } else {
+                    tokens.remove(0)
+                };
+                stack.push(Chunk {
+                    name,
+                    params: tokens,
+                    entries: Vec::new(),
+                });
+            } else if line == ">" {
+                let chunk = stack
+                    .pop()
+                    .ok_or_else(|| TaikoError::RppParse("unbalanced '>'".to_string()))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.entries.push(Entry::Chunk(chunk)),
+                    None => root = Some(chunk),
+                }
+            } else if let Some(chunk) = stack.last_mut() {
+                if !line.is_empty() {
+                    chunk.entries.push(Entry::Line(tokenize(line)));
+                }
+            }
+        }
+
+        match (root, stack.is_empty()) {
+            (Some(root), true) => Ok(root),
+            _ => Err(TaikoError::RppParse("unterminated chunk".to_string())),
+        }
+    }
+
+    /// The project tempo and time signature, followed by the points of the tempo envelope.
+    fn tempo_points(project: &Chunk) -> Result<Vec<TempoPoint>, TaikoError> {
+        let tempo = project.value("TEMPO").unwrap_or_default();
+        let bpm = tempo
+            .first()
+            .and_then(|bpm| bpm.parse::<f64>().ok())
+            .unwrap_or(120_f64);
+        let numerator = tempo.get(1).and_then(|n| n.parse::<u8>().ok()).unwrap_or(4);
+        let denominator = tempo.get(2).and_then(|d| d.parse::<u8>().ok()).unwrap_or(4);
+
+        let mut points = vec![TempoPoint {
+            seconds: 0_f64,
+            beat: 0_f64,
+            bpm,
+            time_signature: Some((numerator, denominator)),
+        }];
+
+        if let Some(envelope) = project.children("TEMPOENVEX").next() {
+            for line in envelope.lines().filter(|line| line[0] == "PT") {
+                // PT seconds bpm shape [time signature as numerator + denominator * 65536]
+                let number = |i: usize| line.get(i).and_then(|value| value.parse::<f64>().ok());
+                let (Some(seconds), Some(bpm)) = (number(1), number(2)) else {
+                    return Err(TaikoError::RppParse(format!(
+                        "invalid tempo point \"{}\"",
+                        line.join(" ")
+                    )));
+                };
+                let time_signature = number(4)
+                    .map(|packed| packed as u32)
+                    .filter(|&packed| packed != 0)
+                    .map(|packed| ((packed & 0xFFFF) as u8, (packed >> 16) as u8))
+                    .filter(|&(numerator, denominator)| {
+                        numerator > 0 && denominator.is_power_of_two()
+                    });
+
+                let last = points
+                    .last_mut()
+                    .expect("points start with the project tempo");
+                if seconds <= last.seconds {
+                    // A point at the start of the project replaces its tempo.
+                    last.bpm = bpm;
+                    last.time_signature = time_signature.or(last.time_signature);
+                    continue;
+                }
+                let beat = last.beat + (seconds - last.seconds) * last.bpm / 60_f64;
+                points.push(TempoPoint {
+                    seconds,
+                    beat,
+                    bpm,
+                    time_signature,
+                });
+            }
+        }
+
+        Ok(points)
+    }
+
+    /// Converts a position in seconds into quarter note beats.
+    fn beat_at(tempo: &[TempoPoint], seconds: f64) -> f64 {
+        let point = tempo
+            .iter()
+            .take_while(|point| point.seconds <= seconds)
+            .last()
+            .unwrap_or(&tempo[0]);
+        point.beat + (seconds - point.seconds) * point.bpm / 60_f64
+    }
+
+    /// Beat, channel, pitch and velocity of every note an item plays.
+    fn item_notes(
+        &self,
+        item: &Chunk,
+        tempo: &[TempoPoint],
+    ) -> Result<Vec<(f64, u8, u8, u8)>, TaikoError> {
+        let Some(source) = item.children("SOURCE").find(|source| {
+            matches!(
+                source.params.first().map(String::as_str),
+                Some("MIDI" | "MIDIPOOL")
+            )
+        }) else {
+            return Ok(Vec::new());
+        };
+
+        let position = item.number("POSITION").unwrap_or_default();
+        let length = item.number("LENGTH").unwrap_or(f64::INFINITY);
+        let start = RppProject::beat_at(tempo, position);
+        let end = RppProject::beat_at(tempo, position + length);
+        // The item starts this far into its source.
+        let source_offset =
+            RppProject::beat_at(tempo, position + item.number("SOFFS").unwrap_or_default()) - start;
+
+        let notes = match source.value("FILE").and_then(|file| file.first()) {
+            Some(file) => self.file_notes(file)?,
+            None => RppProject::in_project_notes(source),
+        };
+
+        Ok(notes
+            .into_iter()
+            .map(|(beat, channel, key, velocity)| {
+                (start + beat - source_offset, channel, key, velocity)
+            })
+            .filter(|&(beat, ..)| beat >= start && beat < end)
+            .collect())
+    }
+
+    /// Notes of an in-project midi source, in beats from the start of the source.
+    fn in_project_notes(source: &Chunk) -> Vec<(f64, u8, u8, u8)> {
+        let ppq = source
+            .value("HASDATA")
+            .and_then(|data| data.get(1))
+            .and_then(|ppq| ppq.parse::<f64>().ok())
+            .filter(|&ppq| ppq > 0_f64)
+            .unwrap_or(RppProject::DEFAULT_SOURCE_PPQ);
+
+        let mut notes = Vec::<(f64, u8, u8, u8)>::new();
+        let mut ticks = 0_u64;
+        for entry in source.entries.iter() {
+            // E/e lines are events, X lines and <X chunks are sysex and meta events. Every
+            // one of them advances the time by its delta; an 'm' marks a muted event.
+            let (kind, tokens) = match entry {
+                Entry::Line(line) => (line[0].as_str(), &line[1..]),
+                Entry::Chunk(chunk) => (chunk.name.as_str(), chunk.params.as_slice()),
+            };
+            if !matches!(kind, "E" | "e" | "Em" | "em" | "X" | "x" | "Xm" | "xm") {
+                continue;
+            }
+            ticks += tokens
+                .first()
+                .and_then(|delta| delta.parse::<u64>().ok())
+                .unwrap_or_default();
+            if !matches!(kind, "E" | "e") {
+                continue;
+            }
+
+            let byte = |i: usize| tokens.get(i).and_then(|b| u8::from_str_radix(b, 16).ok());
+            if let (Some(status), Some(key), Some(velocity)) = (byte(1), byte(2), byte(3)) {
+                if status & 0xF0 == 0x90 && velocity > 0 && key < 128 && velocity < 128 {
+                    notes.push((ticks as f64 / ppq, status & 0x0F, key, velocity));
+                }
+            }
+        }
+
+        notes
+    }
+
+    /// Notes of a referenced midi file, in beats from its start.
+    fn file_notes(&self, file: &str) -> Result<Vec<(f64, u8, u8, u8)>, TaikoError> {
+        let path = self.folder.join(file);
+        let bytes = std::fs::read(&path).map_err(TaikoError::io(&path))?;
+        let smf = Smf::parse(&bytes)?;
+        let Timing::Metrical(ppq) = smf.header.timing else {
+            return Err(TaikoError::RppParse(format!(
+                "{} uses timecode, which referenced midi files do not support",
+                path.display()
+            )));
+        };
+        let ppq = ppq.as_int() as f64;
+
+        let mut notes = Vec::<(f64, u8, u8, u8)>::new();
+        for track in smf.tracks.iter() {
+            let mut ticks = 0_u64;
+            for event in track.iter() {
+                ticks += event.delta.as_int() as u64;
+                if let TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::NoteOn { key, vel },
+                } = event.kind
+                {
+                    if vel > 0 {
+                        notes.push((
+                            ticks as f64 / ppq,
+                            channel.as_int(),
+                            key.as_int(),
+                            vel.as_int(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(notes)
+    }
+}
+
+/// Splits a project line into tokens. Tokens may be quoted with ", ' or `.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::<String>::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if matches!(c, '"' | '\'' | '`') {
+            chars.next();
+            tokens.push(chars.by_ref().take_while(|&next| next != c).collect());
+        } else {
+            let mut token = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_whitespace() {
+                    break;
+                }
+                token.push(next);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+
+    tokens
+}
@@ -0,0 +1,473 @@
+use std::{borrow::Cow, collections::BTreeMap, fmt::Write, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+    converters::round_to,
+    error::TaikoError,
+    json_structures::{
+        custom::{Config, SongMetadata},
+        edda_objects::{Bpmchange, CustomData, Note, Root},
+    },
+};
+
+/// The only chart type read and written, with one column per Ragnarock lane.
+const STEPS_TYPE: &str = "dance-single";
+const PANELS: usize = 4;
+/// StepMania's finest resolution is a 192nd note, 48 rows per beat.
+const ROWS_PER_BEAT: i64 = 48;
+/// Row counts StepMania uses for a measure, from quarter to 192nd notes.
+const MEASURE_ROWS: [i64; 10] = [4, 8, 12, 16, 24, 32, 48, 64, 96, 192];
+
+/// A StepMania chart read into Edda beatmaps.
+pub struct StepmaniaSong {
+    pub song: SongMetadata,
+    pub difficulties: Vec<(String, Root)>,
+}
+
+/// Reads the 4-panel `dance-single` charts of a StepMania .sm or .ssc file.
+///
+/// Edda has neither an audio offset nor stops, so both become bpm changes that keep every
+/// note at its time in seconds. A negative `#OFFSET` adds lead-in beats before the chart,
+/// a positive one shortens its first beats, and a stop slows down the 192nd note after it.
+///
+/// ```no_run
+/// # fn main() -> Result<(), taiko::TaikoError> {
+/// let song = taiko::stepmania::SmImporter::from_file("song.sm")?.import()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SmImporter<'a> {
+    text: Cow<'a, str>,
+    configuration: Cow<'a, Config>,
+}
+
+#[derive(Default)]
+struct Chart {
+    steps_type: String,
+    difficulty: String,
+    notes: String,
+    bpms: Option<String>,
+    stops: Option<String>,
+    offset: Option<String>,
+}
+
+impl<'a> SmImporter<'a> {
+    /// Creates an importer over the text of a chart using the default config.
+    pub fn from_text(text: impl Into<Cow<'a, str>>) -> Self {
+        SmImporter {
+            text: text.into(),
+            configuration: Cow::Owned(Config::default()),
+        }
+    }
+
+    /// Reads the .sm or .ssc file at `path` into an importer.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TaikoError> {
+        let text = std::fs::read_to_string(path.as_ref()).map_err(TaikoError::io(path))?;
+        Ok(SmImporter::from_text(text))
+    }
+
+    pub fn with_config(mut self, configuration: &'a Config) -> Self {
+        self.configuration = Cow::Borrowed(configuration);
+        self
+    }
+
+    /// Converts every `dance-single` chart into a beatmap named after its difficulty.
+    pub fn import(&self) -> Result<StepmaniaSong, TaikoError> {
+        let mut song = SongMetadata::default();
+        let mut song_chart = Chart::default();
+        let mut charts = Vec::<Chart>::new();
+        // .ssc files open every chart with #NOTEDATA, and its tags follow until the next one.
+        let mut in_note_data = false;
+
+        for (tag, value) in SmImporter::tags(&self.text) {
+            match tag.as_str() {
+                "TITLE" => song.song_name = value,
+                "ARTIST" => song.song_author_name = value,
+                "MUSIC" => song.song_filename = value,
+                "BACKGROUND" | "JACKET" if !value.is_empty() => song.cover_image_filename = value,
+                "NOTEDATA" => {
+                    in_note_data = true;
+                    charts.push(Chart::default());
+                }
+                "NOTES" if !in_note_data => {
+                    // steps type:description:difficulty:meter:radar values:notes
+                    let fields: Vec<&str> = value.splitn(6, ':').collect();
+                    if fields.len() < 6 {
+                        return Err(parse_error("#NOTES", &value));
+                    }
+                    charts.push(Chart {
+                        steps_type: fields[0].trim().to_string(),
+                        difficulty: fields[2].trim().to_string(),
+                        notes: fields[5].to_string(),
+                        ..Default::default()
+                    });
+                }
+                _ => {
+                    let chart = match charts.last_mut() {
+                        Some(chart) if in_note_data => chart,
+                        _ => &mut song_chart,
+                    };
+                    match tag.as_str() {
+                        "BPMS" => chart.bpms = Some(value),
+                        "STOPS" => chart.stops = Some(value),
+                        "OFFSET" => chart.offset = Some(value),
+                        "STEPSTYPE" => chart.steps_type = value,
+                        "DIFFICULTY" => chart.difficulty = value,
+                        "NOTES" => chart.notes = value,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let mut difficulties = Vec::<(String, Root)>::new();
+        for chart in charts
+            .iter()
+            .filter(|chart| chart.steps_type.eq_ignore_ascii_case(STEPS_TYPE))
+        {
+            let bpms = chart.bpms.as_ref().or(song_chart.bpms.as_ref());
+            let stops = chart.stops.as_ref().or(song_chart.stops.as_ref());
+            let offset = chart.offset.as_ref().or(song_chart.offset.as_ref());
+            let root = self.chart_to_root(chart, bpms, stops, offset)?;
+
+            let mut name = edda_difficulty(&chart.difficulty);
+            let base = name.clone();
+            let mut n = 2;
+            while difficulties.iter().any(|(existing, _)| *existing == name) {
+                name = format!("{} {}", base, n);
+                n += 1;
+            }
+            difficulties.push((name, root));
+        }
+
+        if difficulties.is_empty() {
+            return Err(TaikoError::EmptyTrack);
+        }
+
+        Ok(StepmaniaSong { song, difficulties })
+    }
+
+    /// Every `#TAG:value;` pair in file order, with comments removed.
+    fn tags(text: &str) -> Vec<(String, String)> {
+        let stripped: Vec<&str> = text
+            .lines()
+            .map(|line| line.split("//").next().unwrap_or_default())
+            .collect();
+        let stripped = stripped.join("\n");
+
+        let mut tags = Vec::<(String, String)>::new();
+        let mut rest = stripped.as_str();
+        while let Some(start) = rest.find('#') {
+            rest = &rest[start + 1..];
+            let Some(colon) = rest.find(':') else {
+                break;
+            };
+            let tag = rest[..colon].trim().to_ascii_uppercase();
+            rest = &rest[colon + 1..];
+            let end = rest.find(';').unwrap_or(rest.len());
+            tags.push((tag, rest[..end].trim().to_string()));
+            rest = &rest[(end + 1).min(rest.len())..];
+        }
+
+        tags
+    }
+
+    fn chart_to_root(
+        &self,
+        chart: &Chart,
+        bpms: Option<&String>,
+        stops: Option<&String>,
+        offset: Option<&String>,
+    ) -> Result<Root, TaikoError> {
+        let bpms = match bpms {
+            Some(bpms) => parse_pairs("#BPMS", bpms)?,
+            None => Vec::new(),
+        };
+        let stops = match stops {
+            Some(stops) => parse_pairs("#STOPS", stops)?,
+            None => Vec::new(),
+        };
+        let offset = match offset {
+            Some(offset) if !offset.is_empty() => offset
+                .parse::<f64>()
+                .map_err(|_| parse_error("#OFFSET", offset))?,
+            _ => 0_f64,
+        };
+        let Some(&(_, first_bpm)) = bpms.first() else {
+            return Err(TaikoError::NoTempo);
+        };
+        let bpm_at = |beat: f64| {
+            bpms.iter()
+                .take_while(|(change, _)| *change <= beat)
+                .last()
+                .map_or(first_bpm, |&(_, bpm)| bpm)
+        };
+
+        // Later entries on the same beat replace earlier ones.
+        let mut changes = BTreeMap::<i64, (f64, f64)>::new();
+        let mut push = |beat: f64, bpm: f64| {
+            changes.insert((beat * 1_000_000_f64).round() as i64, (beat, bpm));
+        };
+
+        let lead_in = -offset;
+        let mut shift = 0_f64;
+        if lead_in > 0_f64 {
+            shift = (lead_in * first_bpm / 60_f64).round().max(1_f64);
+        }
+        for &(beat, bpm) in bpms.iter() {
+            push(beat + shift, bpm);
+        }
+        if lead_in > 0_f64 {
+            push(0_f64, 60_f64 * shift / lead_in);
+        } else if lead_in < 0_f64 {
+            let beats = (-lead_in * first_bpm / 60_f64).floor() + 1_f64;
+            push(
+                0_f64,
+                60_f64 * beats / (beats * 60_f64 / first_bpm + lead_in),
+            );
+            push(beats, bpm_at(beats));
+        }
+        for &(beat, seconds) in stops.iter() {
+            let bpm = bpm_at(beat);
+            let row = 1_f64 / ROWS_PER_BEAT as f64;
+            let duration = row * 60_f64 / bpm + seconds;
+            if duration > 0_f64 {
+                push(beat + shift, 60_f64 * row / duration);
+                push(beat + shift + row, bpm);
+            }
+        }
+
+        let bpmchanges = changes
+            .into_values()
+            .map(|(time, bpm)| Bpmchange {
+                bpm,
+                time,
+                beats_per_bar: 4,
+                metronome_offset: 4,
+            })
+            .collect();
+
+        let mut notes = Vec::<Note>::new();
+        for (measure, rows) in chart.notes.split(',').enumerate() {
+            let rows: Vec<&str> = rows
+                .lines()
+                .map(str::trim)
+                .filter(|row| !row.is_empty())
+                .collect();
+            for (i, row) in rows.iter().enumerate() {
+                let beat = 4_f64 * measure as f64 + 4_f64 * i as f64 / rows.len() as f64;
+                for (panel, step) in row.chars().take(PANELS).enumerate() {
+                    // Taps, hold heads and roll heads. Tails, mines and fakes are not hits.
+                    if matches!(step, '1' | '2' | '4') {
+                        notes.push(Note {
+                            line_index: panel as i64,
+                            time: round_to(beat + shift, self.configuration.time_precision),
+                            line_layer: 1,
+                            type_field: 0,
+                            cut_direction: 1,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(Root {
+            version: "1".to_string(),
+            custom_data: CustomData {
+                time: 0,
+                bpmchanges,
+                bookmarks: Vec::<Value>::new(),
+            },
+            events: Vec::<Value>::new(),
+            notes,
+            obstacles: Vec::<Value>::new(),
+        })
+    }
+}
+
+/// Writes converted beatmaps as the `dance-single` charts of a StepMania .sm file.
+/// Lanes become panels left to right, and notes are placed on the nearest 192nd note.
+/// Notes in lanes beyond the four panels are left out, see [`SmExporter::dropped_notes`].
+pub struct SmExporter<'a> {
+    song: &'a SongMetadata,
+    difficulties: &'a [(String, Root)],
+}
+
+impl<'a> SmExporter<'a> {
+    pub fn new(song: &'a SongMetadata, difficulties: &'a [(String, Root)]) -> Self {
+        SmExporter { song, difficulties }
+    }
+
+    /// File name of the chart, e.g. `Song.sm`.
+    pub fn file_name(&self) -> String {
+        let name: String = self
+            .song
+            .song_name
+            .chars()
+            .filter(|c| !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+            .collect();
+        if name.is_empty() {
+            "song.sm".to_string()
+        } else {
+            format!("{}.sm", name)
+        }
+    }
+
+    /// Difficulties with notes that have no panel, e.g. from a drum map with more than four
+    /// lanes, and how many of their notes the .sm file leaves out.
+    pub fn dropped_notes(&self) -> Vec<(String, usize)> {
+        self.difficulties
+            .iter()
+            .map(|(name, root)| {
+                let dropped = root
+                    .notes
+                    .iter()
+                    .filter(|note| SmExporter::panel(note).is_none())
+                    .count();
+                (name.clone(), dropped)
+            })
+            .filter(|&(_, dropped)| dropped > 0)
+            .collect()
+    }
+
+    /// Renders the charts in the .sm file format.
+    pub fn to_sm_string(&self) -> String {
+        let song = self.song;
+        let mut sm = String::new();
+        let _ = writeln!(sm, "#TITLE:{};", song.song_name);
+        let _ = writeln!(sm, "#ARTIST:{};", song.song_author_name);
+        let _ = writeln!(sm, "#CREDIT:taiko;");
+        let _ = writeln!(sm, "#MUSIC:{};", song.song_filename);
+        let _ = writeln!(sm, "#BACKGROUND:{};", song.cover_image_filename);
+        let _ = writeln!(sm, "#OFFSET:0.000000;");
+
+        // Every difficulty comes from the same tempo map.
+        let bpms: Vec<String> = self
+            .difficulties
+            .first()
+            .map(|(_, root)| root.custom_data.bpmchanges.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|change| format!("{:.6}={:.6}", change.time, change.bpm))
+            .collect();
+        let _ = writeln!(sm, "#BPMS:{};", bpms.join(",\n"));
+        let _ = writeln!(sm, "#STOPS:;");
+
+        for (rank, (name, root)) in self.difficulties.iter().enumerate() {
+            let _ = write!(
+                sm,
+                "\n//--------------- {} - {} ----------------\n#NOTES:\n     {}:\n     {}:\n     {}:\n     {}:\n     0,0,0,0,0:\n",
+                STEPS_TYPE,
+                name,
+                STEPS_TYPE,
+                name,
+                sm_difficulty(name),
+                rank + 1
+            );
+            sm.push_str(&SmExporter::measures(root));
+            sm.push_str(";\n");
+        }
+
+        sm
+    }
+
+    /// The panel of a note, if it has one and is not before the first beat.
+    fn panel(note: &Note) -> Option<usize> {
+        usize::try_from(note.line_index)
+            .ok()
+            .filter(|&panel| panel < PANELS && note.time >= 0_f64)
+    }
+
+    /// The note data of a beatmap, one measure of four beats per block.
+    fn measures(root: &Root) -> String {
+        let mut steps = BTreeMap::<i64, [char; PANELS]>::new();
+        for note in root.notes.iter() {
+            let Some(panel) = SmExporter::panel(note) else {
+                continue;
+            };
+            let row = (note.time * ROWS_PER_BEAT as f64).round() as i64;
+            steps.entry(row).or_insert(['0'; PANELS])[panel] = '1';
+        }
+
+        let rows_per_measure = 4 * ROWS_PER_BEAT;
+        let measure_count = steps
+            .keys()
+            .last()
+            .map_or(1, |row| row / rows_per_measure + 1);
+
+        let mut measures = Vec::<String>::new();
+        for measure in 0..measure_count {
+            let start = measure * rows_per_measure;
+            let in_measure: Vec<(i64, [char; PANELS])> = steps
+                .range(start..start + rows_per_measure)
+                .map(|(row, panels)| (row - start, *panels))
+                .collect();
+            let rows = MEASURE_ROWS
+                .iter()
+                .copied()
+                .find(|rows| {
+                    let step = rows_per_measure / rows;
+                    in_measure.iter().all(|(row, _)| row % step == 0)
+                })
+                .unwrap_or(rows_per_measure);
+            let step = rows_per_measure / rows;
+
+            let mut text = String::new();
+            for row in 0..rows {
+                let panels = in_measure
+                    .iter()
+                    .find(|(at, _)| *at == row * step)
+                    .map_or(['0'; PANELS], |(_, panels)| *panels);
+                text.extend(panels.iter());
+                text.push('\n');
+            }
+            measures.push(text);
+        }
+
+        measures.join(",\n")
+    }
+}
+
+/// Parses `beat=value` lists such as `#BPMS` and `#STOPS`.
+fn parse_pairs(tag: &str, value: &str) -> Result<Vec<(f64, f64)>, TaikoError> {
+    let mut pairs = Vec::<(f64, f64)>::new();
+    for pair in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parsed = pair.split_once('=').and_then(|(beat, value)| {
+            Some((beat.trim().parse().ok()?, value.trim().parse().ok()?))
+        });
+        match parsed {
+            Some(pair) => pairs.push(pair),
+            None => return Err(parse_error(tag, value)),
+        }
+    }
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    Ok(pairs)
+}
+
+fn parse_error(tag: &str, value: &str) -> TaikoError {
+    TaikoError::StepmaniaParse(format!("invalid {} \"{}\"", tag, value))
+}
+
+/// Edda name of a StepMania difficulty.
+fn edda_difficulty(difficulty: &str) -> String {
+    match difficulty.to_ascii_lowercase().as_str() {
+        "medium" => "Normal".to_string(),
+        "challenge" => "Expert".to_string(),
+        "" => "Edit".to_string(),
+        _ => difficulty.to_string(),
+    }
+}
+
+/// StepMania name of an Edda difficulty.
+fn sm_difficulty(difficulty: &str) -> &'static str {
+    match difficulty {
+        "Beginner" => "Beginner",
+        "Easy" => "Easy",
+        "Normal" | "Medium" => "Medium",
+        "Hard" => "Hard",
+        "Expert" => "Challenge",
+        _ => "Edit",
+    }
+}
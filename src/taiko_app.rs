@@ -1,31 +1,129 @@
 use std::{
-    fs::File,
-    io::{self, stdout, Read, Write},
+    fs,
+    io::{self, stdout, Write},
     path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
 
 use clap::Parser;
 use eframe::egui::{self, Id};
 
-use crate::{converters::MidiConverter, file_io, json_structures::custom::Config, Args, Commands};
+use taiko::{
+    backup::{self, Backup},
+    batch, file_io,
+    json_structures::{
+        custom::{
+            BeatRange, Config, InputFormat, LanePitches, OsuHit, ProfileStore, SongMetadata,
+            TimeSignature, UnmappedNotePolicy,
+        },
+        edda_info,
+        edda_objects::Root,
+    },
+    package::SongPackage,
+    stepmania::{SmExporter, SmImporter},
+    watch::{self, SourceWatcher},
+    ConversionReport, DatConverter, MidiConverter, OsuBeatmap, TaikoError,
+};
+
+use crate::{Args, Commands, ConfigCommands, InputArgs, OutputArgs, ProfileCommands};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const NAME: &str = env!("CARGO_PKG_NAME");
 
+/// The config file profiles are read from and saved to.
+pub struct ConfigLocation {
+    pub path: PathBuf,
+    /// Picked with --config, which also turns off taiko.json project files.
+    pub forced: bool,
+}
+
+impl ConfigLocation {
+    /// config.json in the platform config directory, e.g. ~/.config/taiko on Linux.
+    pub fn global() -> Self {
+        let path = directories::ProjectDirs::from("", "", NAME)
+            .map(|dirs| dirs.config_dir().join("config.json"))
+            .unwrap_or_else(|| PathBuf::from("config.json"));
+        ConfigLocation {
+            path,
+            forced: false,
+        }
+    }
+
+    pub fn forced(path: PathBuf) -> Self {
+        ConfigLocation { path, forced: true }
+    }
+
+    /// The taiko.json that applies to `source`, unless project files are turned off.
+    fn project_file(&self, source: impl AsRef<Path>) -> Option<PathBuf> {
+        if self.forced {
+            None
+        } else {
+            file_io::find_project_file(source)
+        }
+    }
+
+    /// `config` with the taiko.json of `source` applied, and the file's path if there is one.
+    fn config_for_source(
+        &self,
+        config: &Config,
+        source: impl AsRef<Path>,
+    ) -> Result<(Config, Option<PathBuf>), TaikoError> {
+        match self.project_file(source) {
+            Some(path) => Ok((file_io::apply_project_file(config, &path)?, Some(path))),
+            None => Ok((config.clone(), None)),
+        }
+    }
+}
+
+impl Default for ConfigLocation {
+    fn default() -> Self {
+        ConfigLocation::global()
+    }
+}
+
 #[derive(Default)]
 pub struct TaikoApp {
     pub source_path: Option<String>,
     pub output_path: Option<String>,
     pub output_type: ComboBoxConversion,
     pub difficulty: Difficulty,
+    /// The configuration in use, with edits not yet saved to its profile.
     pub config: Config,
+    pub profiles: ProfileStore,
+    pub config_location: ConfigLocation,
+    /// Name for a new or copied profile.
+    pub profile_name: String,
+    /// The text of each lane's pitch field, kept while it does not parse.
+    pub drum_map_text: Vec<String>,
+    /// Name the current drum map is saved under as a preset.
+    pub preset_name: String,
+    pub song: SongMetadata,
+    pub audio_path: Option<PathBuf>,
+    pub cover_path: Option<PathBuf>,
+    pub package_zip: bool,
+    /// Replaces only the notes and bpm changes of existing .dat files.
+    pub update_existing: bool,
+    /// Beat range to update, e.g. "16-32". Empty updates the whole map.
+    pub update_beats: String,
+    /// Converts the source again whenever it changes.
+    pub watch: bool,
+    watcher: Option<SourceWatcher>,
+    /// Note count of every difficulty written by the last conversion.
+    note_counts: Vec<(String, usize)>,
+    /// Backups in the output folder, and the folder they were read from.
+    backup_list: Vec<Backup>,
+    backup_folder: Option<String>,
     pub log: Vec<String>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Default)]
 pub enum ComboBoxConversion {
+    #[default]
     SingleOutput,
     MultiOutput,
+    Package,
+    Osu,
+    Stepmania,
 }
 
 impl std::fmt::Display for ComboBoxConversion {
@@ -33,12 +131,16 @@ impl std::fmt::Display for ComboBoxConversion {
         match self {
             Self::SingleOutput => write!(f, "Single"),
             Self::MultiOutput => write!(f, "Multi"),
+            Self::Package => write!(f, "Package"),
+            Self::Osu => write!(f, "osu!taiko"),
+            Self::Stepmania => write!(f, "StepMania"),
         }
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Default)]
 pub enum Difficulty {
+    #[default]
     Easy,
     Normal,
     Hard,
@@ -54,27 +156,36 @@ impl std::fmt::Display for Difficulty {
     }
 }
 
-impl Default for Difficulty {
-    fn default() -> Self {
-        Difficulty::Easy
-    }
-}
-
-impl Default for ComboBoxConversion {
-    fn default() -> Self {
-        ComboBoxConversion::SingleOutput
-    }
-}
-
 impl TaikoApp {
-    pub fn from_config(config: Config) -> Self {
+    pub fn from_profiles(profiles: ProfileStore, config_location: ConfigLocation) -> Self {
+        let config = profiles.active().clone();
         TaikoApp {
-            config: config,
+            drum_map_text: config
+                .drum_map
+                .iter()
+                .map(|lane| lane.to_string())
+                .collect(),
+            config,
+            profiles,
+            config_location,
             ..Default::default()
         }
     }
 
-    pub fn run_gui(self) {
+    /// Starts the gui. A config that cannot be read is reported in the log and the default
+    /// settings are used.
+    pub fn run_gui() {
+        let location = ConfigLocation::global();
+        let (profiles, error) = match TaikoApp::get_or_create_config(&location) {
+            Ok(profiles) => (profiles, None),
+            Err(e) => (ProfileStore::default(), Some(e)),
+        };
+        let mut app = TaikoApp::from_profiles(profiles, location);
+        if let Some(e) = error {
+            app.log_str(format!("Error: {}", e));
+            app.log_str("Save Config will replace it with the settings shown.".to_owned());
+        }
+
         let options = eframe::NativeOptions {
             drag_and_drop_support: true,
             initial_window_size: Some(egui::vec2(570.0, 300.0)),
@@ -82,89 +193,359 @@ impl TaikoApp {
         };
 
         let title = format!("{} v{}", NAME, VERSION);
-        eframe::run_native(title.as_str(), options, Box::new(|_cc| Box::new(self)));
+        eframe::run_native(title.as_str(), options, Box::new(|_cc| Box::new(app)));
     }
 
-    pub fn run_cli(self) {
+    pub fn run_cli() {
         let args = Args::parse();
-        handle_cli_input(args, self.config);
+        let location = match &args.config {
+            Some(path) => ConfigLocation::forced(path.clone()),
+            None => ConfigLocation::global(),
+        };
+        let result = TaikoApp::get_or_create_config(&location)
+            .and_then(|profiles| handle_cli_input(args, profiles, &location));
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(e.exit_code());
+        }
     }
 
     fn log_str(&mut self, msg: String) {
         println!("{}", msg);
         self.log.push(msg);
-        if self.log.len() > 3 {
+        if self.log.len() > 50 {
             self.log.remove(0);
         }
     }
 
-    fn write_output_app(
-        &mut self,
-        path: &String,
-        data: &crate::json_structures::edda_objects::Root,
-    ) {
-        let file_name = path;
-        match File::create(file_name) {
-            Ok(mut file) => {
-                println!("Writing to {} ...", file_name);
-                match file.write_all(
-                    serde_json::to_string_pretty(data)
-                        .unwrap_or_default()
-                        .as_bytes(),
-                ) {
-                    Ok(_) => self.log_str(format!("Success! Wrote to: {}", file_name)),
-                    Err(e) => self.log_str(format!("Failed to create file {}: {}", path, e)),
-                }
-            }
-            Err(e) => self.log_str(format!("Failed to create file {}: {}", path, e)),
+    fn log_report(&mut self, report: &ConversionReport) {
+        for line in report.lines() {
+            self.log_str(line);
         }
     }
 
-    fn save_config_app(&mut self, config: &Config, file: &mut File) {
-        match serde_json::to_string_pretty(config) {
-            Ok(json_str) => {
-                match file.set_len(0) {
-                    Ok(_) => {}
-                    Err(e) => self.log_str(format!("Failed to save config: {}", e)),
-                };
-                match file.write_all(json_str.as_bytes()) {
-                    Ok(_) => self.log_str(format!("Saved config!")),
-                    Err(e) => self.log_str(format!("Failed to save config: {}", e)),
-                };
-            }
+    /// Backs up the file at `path` before it is overwritten. Returns false if that failed.
+    fn back_up_app(&mut self, path: &String) -> bool {
+        // The backup list is read again the next time it is shown.
+        self.backup_folder = None;
+        let generations = self.config.backup_generations;
+        let backed_up = match self.watcher.as_ref() {
+            Some(watcher) => backup::back_up_once(path, generations, watcher.started()),
+            None => backup::back_up(path, generations),
+        };
+        match backed_up {
+            Ok(_) => true,
             Err(e) => {
-                self.log_str(format!("{}", e));
+                self.log_str(format!("Error: {}", e));
+                false
+            }
+        }
+    }
+
+    fn write_output_app<T: serde::Serialize>(&mut self, path: &String, data: &T) {
+        if !self.back_up_app(path) {
+            return;
+        }
+        match file_io::write_output_json(path, data) {
+            Ok(_) => self.log_str(format!("Success! Wrote to: {}", path)),
+            Err(e) => self.log_str(format!("Error: {}", e)),
+        }
+    }
+
+    /// Writes info.dat, keeping the song details of an existing one.
+    fn write_info_app(&mut self, path: &String, info: &edda_info::Root) {
+        if !self.back_up_app(path) {
+            return;
+        }
+        match file_io::update_info_json(path, info) {
+            Ok(_) => self.log_str(format!("Success! Wrote to: {}", path)),
+            Err(e) => self.log_str(format!("Error: {}", e)),
+        }
+    }
+
+    /// Writes a difficulty, or updates the existing file when Update Existing is checked.
+    fn write_dat_app(&mut self, path: &String, root: &Root) {
+        if !self.update_existing {
+            return self.write_output_app(path, root);
+        }
+
+        let beats = self.update_beats.trim();
+        let range = if beats.is_empty() {
+            None
+        } else {
+            match beats.parse::<BeatRange>() {
+                Ok(range) => Some(range),
+                Err(e) => return self.log_str(format!("Error: {}", e)),
             }
         };
+        if !self.back_up_app(path) {
+            return;
+        }
+        match file_io::update_output_json(path, root, range) {
+            Ok(_) => self.log_str(format!("Success! Updated: {}", path)),
+            Err(e) => self.log_str(format!("Error: {}", e)),
+        }
+    }
+
+    fn save_config_app(&mut self, profiles: &ProfileStore) {
+        match file_io::save_config(profiles, &self.config_location.path) {
+            Ok(_) => self.log_str("Saved config!".to_string()),
+            Err(e) => self.log_str(format!("Failed to save config: {}", e)),
+        }
+    }
+
+    /// Profile picker, with buttons to create, copy and delete profiles.
+    fn show_profiles(&mut self, ui: &mut egui::Ui) {
+        ui.label("Profile:");
+        let mut picked = None;
+        egui::ComboBox::from_id_source("profile")
+            .selected_text(&self.profiles.active_profile)
+            .show_ui(ui, |ui| {
+                for profile in self.profiles.profiles.iter() {
+                    let active = profile.name == self.profiles.active_profile;
+                    if ui.selectable_label(active, &profile.name).clicked() && !active {
+                        picked = Some(profile.name.clone());
+                    }
+                }
+            });
+
+        ui.add(
+            egui::TextEdit::singleline(&mut self.profile_name)
+                .hint_text("Profile name")
+                .desired_width(80_f32),
+        );
+        ui.horizontal(|ui| {
+            let name = self.profile_name.trim().to_owned();
+            if ui
+                .button("New")
+                .on_hover_text("Create a profile with the default configuration")
+                .clicked()
+            {
+                match self.profiles.create(&name, Config::default()) {
+                    Ok(_) => picked = Some(name.clone()),
+                    Err(e) => self.log_str(format!("Error: {}", e)),
+                }
+            }
+            if ui
+                .button("Copy")
+                .on_hover_text("Create a profile with the current configuration")
+                .clicked()
+            {
+                match self.profiles.create(&name, self.config.clone()) {
+                    Ok(_) => picked = Some(name.clone()),
+                    Err(e) => self.log_str(format!("Error: {}", e)),
+                }
+            }
+            if ui.button("Delete").clicked() {
+                let active = self.profiles.active_profile.clone();
+                match self.profiles.delete(&active) {
+                    Ok(_) => {
+                        self.load_profile();
+                        self.log_str(format!(
+                            "Deleted profile {}. Save the config to keep the change.",
+                            active
+                        ));
+                    }
+                    Err(e) => self.log_str(format!("Error: {}", e)),
+                }
+            }
+        });
+
+        if let Some(name) = picked {
+            // Unsaved edits stay with the profile they were made in until the config is saved.
+            let active = self.profiles.active_profile.clone();
+            let switched = self
+                .profiles
+                .set_config(&active, self.config.clone())
+                .and_then(|_| self.profiles.select(&name));
+            match switched {
+                Ok(_) => {
+                    self.load_profile();
+                    self.log_str(format!("Using profile {}", self.profiles.active_profile));
+                }
+                Err(e) => self.log_str(format!("Error: {}", e)),
+            }
+        }
+    }
+
+    /// Shows the configuration of the profile in use.
+    fn load_profile(&mut self) {
+        self.config = self.profiles.active().clone();
+        self.drum_map_text = self
+            .config
+            .drum_map
+            .iter()
+            .map(|lane| lane.to_string())
+            .collect();
     }
 
     fn show_configuration_panel(&mut self, ctx: &egui::Context) {
         egui::SidePanel::right(Id::new("right frame"))
             .default_width(80_f32)
             .show(ctx, |ui| {
-                for (i, midi) in self.config.drum_map.iter_mut().enumerate() {
+                self.show_profiles(ui);
+                ui.separator();
+
+                let mut picked = None;
+                egui::ComboBox::from_id_source("drum map preset")
+                    .selected_text("Preset")
+                    .show_ui(ui, |ui| {
+                        for preset in self.config.presets() {
+                            if ui.selectable_label(false, &preset.name).clicked() {
+                                picked = Some(preset);
+                            }
+                        }
+                    });
+                if let Some(preset) = picked {
+                    self.drum_map_text = preset.drum_map.iter().map(|l| l.to_string()).collect();
+                    self.config.drum_map = preset.drum_map;
+                    self.log_str(format!("Applied preset {}", preset.name));
+                }
+
+                for (i, (lane, text)) in self
+                    .config
+                    .drum_map
+                    .iter_mut()
+                    .zip(self.drum_map_text.iter_mut())
+                    .enumerate()
+                {
                     ui.with_layout(
                         egui::Layout::top_down_justified(eframe::emath::Align::Min),
                         |ui| {
                             ui.label(format!("Drum {}:", i));
-                            ui.add(egui::DragValue::new(midi));
-                            *midi = u8::clamp(*midi, 0, 127);
+                            ui.add(
+                                egui::TextEdit::singleline(text)
+                                    .hint_text("38, 40, 35-36")
+                                    .desired_width(80_f32),
+                            )
+                            .on_hover_text("Pitches and pitch ranges, separated by commas");
+                            let parsed = text
+                                .parse::<LanePitches>()
+                                .and_then(|parsed| parsed.check().map(|_| parsed));
+                            match parsed {
+                                Ok(parsed) => *lane = parsed,
+                                Err(e) => {
+                                    ui.colored_label(egui::Color32::RED, e);
+                                }
+                            }
                         },
                     );
                 }
 
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.preset_name)
+                        .hint_text("Preset name")
+                        .desired_width(80_f32),
+                );
+                if ui.button("Save Preset").clicked() && !self.preset_name.trim().is_empty() {
+                    self.config.save_preset(&self.preset_name);
+                    self.log_str(format!(
+                        "Saved preset {}. Save the config to keep it.",
+                        self.preset_name.trim()
+                    ));
+                }
+
+                ui.label("osu!taiko Hits:");
+                for (i, hit) in self.config.osu_hits.iter_mut().enumerate() {
+                    egui::ComboBox::from_id_source(("osu hit", i))
+                        .selected_text(format!("{}: {}", i, hit))
+                        .show_ui(ui, |ui| {
+                            for option in [OsuHit::Don, OsuHit::Kat, OsuHit::BigDon, OsuHit::BigKat]
+                            {
+                                ui.selectable_value(hit, option, format!("{}", option));
+                            }
+                        });
+                }
+
+                ui.label("Input:");
+                egui::ComboBox::from_id_source("input format")
+                    .selected_text(format!("{}", self.config.input_format))
+                    .show_ui(ui, |ui| {
+                        for format in [InputFormat::Tracks, InputFormat::RockBand] {
+                            ui.selectable_value(
+                                &mut self.config.input_format,
+                                format,
+                                format!("{}", format),
+                            );
+                        }
+                    });
+
+                ui.label("Unmapped Notes:");
+                egui::ComboBox::from_id_source("unmapped notes")
+                    .selected_text(format!("{}", self.config.unmapped_notes))
+                    .show_ui(ui, |ui| {
+                        for policy in [
+                            UnmappedNotePolicy::Error,
+                            UnmappedNotePolicy::Skip,
+                            UnmappedNotePolicy::Nearest,
+                        ] {
+                            ui.selectable_value(
+                                &mut self.config.unmapped_notes,
+                                policy,
+                                format!("{}", policy),
+                            );
+                        }
+                    });
+
+                let mut use_fallback_bpm = self.config.fallback_bpm.is_some();
+                ui.checkbox(&mut use_fallback_bpm, "Fallback BPM");
+                match (use_fallback_bpm, self.config.fallback_bpm.as_mut()) {
+                    (true, Some(bpm)) => {
+                        ui.add(
+                            egui::DragValue::new(bpm)
+                                .clamp_range(Config::MIN_BPM..=Config::MAX_BPM),
+                        );
+                    }
+                    (true, None) => self.config.fallback_bpm = Some(120_f64),
+                    (false, _) => self.config.fallback_bpm = None,
+                }
+
+                let mut use_fallback_meter = self.config.fallback_time_signature.is_some();
+                ui.checkbox(&mut use_fallback_meter, "Fallback Time Signature");
+                match (
+                    use_fallback_meter,
+                    self.config.fallback_time_signature.as_mut(),
+                ) {
+                    (true, Some(time_signature)) => {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut time_signature.numerator)
+                                    .clamp_range(1..=32),
+                            );
+                            ui.label("/");
+                            egui::ComboBox::from_id_source("fallback denominator")
+                                .width(40_f32)
+                                .selected_text(format!("{}", time_signature.denominator))
+                                .show_ui(ui, |ui| {
+                                    for denominator in [1, 2, 4, 8, 16, 32] {
+                                        ui.selectable_value(
+                                            &mut time_signature.denominator,
+                                            denominator,
+                                            format!("{}", denominator),
+                                        );
+                                    }
+                                });
+                        });
+                    }
+                    (true, None) => {
+                        self.config.fallback_time_signature = Some(TimeSignature::default())
+                    }
+                    (false, _) => self.config.fallback_time_signature = None,
+                }
+
                 ui.with_layout(
                     egui::Layout::top_down_justified(eframe::emath::Align::Min),
                     |ui| {
                         if ui.button("Save Config").clicked() {
-                            match file_io::get_or_create_file_rw(&Path::new("config.json")) {
-                                Ok(mut file) => {
-                                    self.save_config_app(&self.config.clone(), &mut file);
-                                }
-                                Err(e) => {
-                                    self.log_str(format!("Could not save config: {}", e));
+                            let mut profiles = self.profiles.clone();
+                            let active = profiles.active_profile.clone();
+                            match profiles.set_config(&active, self.config.clone()) {
+                                Ok(_) => {
+                                    self.save_config_app(&profiles);
+                                    self.profiles = profiles;
                                 }
-                            };
+                                Err(e) => self.log_str(format!("Error: {}", e)),
+                            }
                         }
                     },
                 );
@@ -178,9 +559,14 @@ impl TaikoApp {
             ui.with_layout(layout, |ui| {
                 if !self.log.is_empty() {
                     ui.label("Log:");
-                    for log_line in &self.log {
-                        ui.label(log_line);
-                    }
+                    egui::ScrollArea::vertical()
+                        .max_height(60_f32)
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            for log_line in &self.log {
+                                ui.label(log_line);
+                            }
+                        });
                 }
             });
         });
@@ -201,6 +587,21 @@ impl TaikoApp {
                         ComboBoxConversion::MultiOutput,
                         "Multi",
                     );
+                    ui.selectable_value(
+                        &mut self.output_type,
+                        ComboBoxConversion::Package,
+                        "Package",
+                    );
+                    ui.selectable_value(
+                        &mut self.output_type,
+                        ComboBoxConversion::Osu,
+                        "osu!taiko",
+                    );
+                    ui.selectable_value(
+                        &mut self.output_type,
+                        ComboBoxConversion::Stepmania,
+                        "StepMania",
+                    );
                 });
 
             if self.output_type == ComboBoxConversion::SingleOutput {
@@ -213,11 +614,67 @@ impl TaikoApp {
                     });
             }
 
+            if self.output_type != ComboBoxConversion::SingleOutput {
+                ui.horizontal(|ui| {
+                    ui.label("Song Name:");
+                    ui.text_edit_singleline(&mut self.song.song_name);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Author:");
+                    ui.text_edit_singleline(&mut self.song.song_author_name);
+                });
+            }
+
+            if self.output_type == ComboBoxConversion::MultiOutput
+                || self.output_type == ComboBoxConversion::Osu
+                || self.output_type == ComboBoxConversion::Stepmania
+            {
+                ui.horizontal(|ui| {
+                    ui.label("Song File:");
+                    ui.text_edit_singleline(&mut self.song.song_filename);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Cover File:");
+                    ui.text_edit_singleline(&mut self.song.cover_image_filename);
+                });
+            }
+
+            if self.output_type == ComboBoxConversion::Package {
+                ui.horizontal(|ui| {
+                    if ui.button("Select Audio").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Audio Files", &["ogg", "OGG"])
+                            .pick_file()
+                        {
+                            self.audio_path = Some(path);
+                        }
+                    }
+                    if let Some(audio) = &self.audio_path {
+                        ui.monospace(audio.file_name().unwrap_or_default().to_string_lossy());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Select Cover").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Image Files", &["jpg", "jpeg", "png"])
+                            .pick_file()
+                        {
+                            self.cover_path = Some(path);
+                        }
+                    }
+                    if let Some(cover) = &self.cover_path {
+                        ui.monospace(cover.file_name().unwrap_or_default().to_string_lossy());
+                    }
+                });
+                ui.checkbox(&mut self.package_zip, "Zip");
+            }
+
             ui.horizontal(|ui| {
                 if ui.button("Select Source").clicked() {
                     let filter = ["midi", "mid", "MIDI", "MID"];
                     if let Some(path) = rfd::FileDialog::new()
                         .add_filter("Midi Files", &filter)
+                        .add_filter("REAPER Projects", &["rpp", "RPP"])
                         .pick_file()
                     {
                         self.source_path = Some(path.display().to_string());
@@ -256,7 +713,7 @@ impl TaikoApp {
                             self.difficulty
                         )
                     }
-                    ComboBoxConversion::MultiOutput => format!(
+                    ComboBoxConversion::MultiOutput | ComboBoxConversion::Package => format!(
                         "{}{}{:?}, {:?}, {:?}.dat",
                         &picked_path,
                         std::path::MAIN_SEPARATOR,
@@ -264,6 +721,12 @@ impl TaikoApp {
                         Difficulty::Normal,
                         Difficulty::Hard
                     ),
+                    ComboBoxConversion::Osu => {
+                        format!("{}{}*.osu", &picked_path, std::path::MAIN_SEPARATOR)
+                    }
+                    ComboBoxConversion::Stepmania => {
+                        format!("{}{}*.sm", &picked_path, std::path::MAIN_SEPARATOR)
+                    }
                 };
 
                 let def = path.as_str();
@@ -278,86 +741,334 @@ impl TaikoApp {
                 });
             }
 
+            if self.output_type == ComboBoxConversion::SingleOutput
+                || self.output_type == ComboBoxConversion::MultiOutput
+            {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.update_existing, "Update Existing")
+                        .on_hover_text(
+                            "Keep the events, obstacles and bookmarks of existing .dat files \
+                             and replace only their notes and bpm changes",
+                        );
+                    if self.update_existing {
+                        ui.label("Beats:");
+                        ui.text_edit_singleline(&mut self.update_beats)
+                            .on_hover_text("Only update these beats, e.g. 16-32. Empty for all.");
+                    }
+                });
+            }
+
             if let (Some(source), Some(output)) =
                 (&self.source_path.clone(), &self.output_path.clone())
             {
-                if ui.button("Run").clicked() {
-                    self.log.clear();
-                    match self.output_type {
-                        ComboBoxConversion::SingleOutput => {
-                            match MidiConverter::new(source.to_string(), &self.config)
-                                .to_root_merge_notes_and_meta()
-                            {
-                                Ok(r) => {
-                                    let mut path_buf = PathBuf::new();
-                                    path_buf.push(output);
-                                    path_buf.push(format!(
-                                        "{}{}",
-                                        self.difficulty, &self.config.batch_output_extension
-                                    ));
-                                    self.write_output_app(
-                                        &path_buf
-                                            .into_os_string()
-                                            .into_string()
-                                            .unwrap_or_default(),
-                                        &r,
-                                    );
-                                }
-                                Err(e) => self.log_str(format!("Error: {}", e)),
-                            };
+                ui.horizontal(|ui| {
+                    if ui.button("Run").clicked() {
+                        self.log.clear();
+                        if let Some(counts) = self.run_conversion(source, output) {
+                            self.note_counts = counts;
                         }
-                        ComboBoxConversion::MultiOutput => {
-                            match MidiConverter::new(source.to_string(), &self.config)
-                                .to_root_merge_meta()
-                            {
-                                Ok(r) => {
-                                    for res in r {
-                                        let mut path_buf = PathBuf::new();
-                                        path_buf.push(output.clone());
-                                        path_buf.push(format!(
-                                            "{}{}",
-                                            &res.0, &self.config.batch_output_extension
-                                        ));
-                                        self.write_output_app(
-                                            &path_buf
-                                                .into_os_string()
-                                                .into_string()
-                                                .unwrap_or_default(),
-                                            &res.1,
-                                        );
-                                    }
-                                }
-                                Err(e) => self.log_str(format!("Error: {}", e)),
-                            }
+                    }
+                    if self.output_type == ComboBoxConversion::SingleOutput
+                        || self.output_type == ComboBoxConversion::MultiOutput
+                    {
+                        ui.checkbox(&mut self.watch, "Watch")
+                            .on_hover_text("Convert again whenever the source file changes");
+                    }
+                });
+            }
+
+            if let Some(output) = self.output_path.clone() {
+                egui::CollapsingHeader::new("Backups")
+                    .show(ui, |ui| self.show_backups(ui, &output));
+            }
+        });
+    }
+
+    /// Lists the backups in the output folder, each with a button to restore it.
+    fn show_backups(&mut self, ui: &mut egui::Ui, output: &String) {
+        if ui.button("Refresh").clicked() || self.backup_folder.as_ref() != Some(output) {
+            self.backup_list = backup::folder_backups(output).unwrap_or_default();
+            self.backup_folder = Some(output.clone());
+        }
+        if self.backup_list.is_empty() {
+            ui.label("No backups yet.");
+            return;
+        }
+
+        let mut restore = None;
+        egui::ScrollArea::vertical()
+            .id_source("backups")
+            .max_height(120_f32)
+            .show(ui, |ui| {
+                for backup in self.backup_list.iter() {
+                    ui.horizontal(|ui| {
+                        let name = backup.original.file_name().unwrap_or_default();
+                        ui.monospace(name.to_string_lossy());
+                        ui.label(backup.time());
+                        if ui.button("Restore").clicked() {
+                            restore = Some(backup.clone());
                         }
-                    };
+                    });
                 }
+            });
+        if let Some(backup) = restore {
+            match backup.restore(self.config.backup_generations) {
+                Ok(_) => self.log_str(format!(
+                    "Restored {} from {}",
+                    backup.original.display(),
+                    backup.time()
+                )),
+                Err(e) => self.log_str(format!("Error: {}", e)),
             }
-        });
+            self.backup_folder = None;
+        }
+    }
+
+    /// Converts the source again once it has changed, while Watch is checked.
+    fn watch_source(&mut self, ctx: &egui::Context) {
+        let watching = self.watch
+            && (self.output_type == ComboBoxConversion::SingleOutput
+                || self.output_type == ComboBoxConversion::MultiOutput);
+        let (true, Some(source), Some(output)) =
+            (watching, self.source_path.clone(), self.output_path.clone())
+        else {
+            self.watcher = None;
+            return;
+        };
+
+        if self.watcher.as_ref().map(|w| w.path()) != Some(Path::new(&source)) {
+            self.watcher = Some(SourceWatcher::new(&source, watch::DEFAULT_DEBOUNCE));
+            self.log_str(format!("Watching {} for changes.", source));
+        }
+        if self.watcher.as_mut().is_some_and(|w| w.poll()) {
+            self.log_str(format!("{} changed, converting again ...", source));
+            if let Some(counts) = self.run_conversion(&source, &output) {
+                for line in watch::count_changes(&self.note_counts, &counts) {
+                    self.log_str(line);
+                }
+                self.note_counts = counts;
+            }
+        }
+        ctx.request_repaint_after(watch::POLL_INTERVAL);
     }
 
-    pub fn get_or_create_config() -> Config {
-        let path = Path::new("config.json");
+    /// Runs the selected conversion. Returns the note count of every difficulty written for
+    /// Single and Multi outputs, which watch mode compares between runs.
+    fn run_conversion(&mut self, source: &str, output: &str) -> Option<Vec<(String, usize)>> {
+        let project_config = match self.config_location.config_for_source(&self.config, source) {
+            Ok((config, path)) => {
+                if let Some(path) = path {
+                    self.log_str(format!("Using settings from {}", path.display()));
+                }
+                config
+            }
+            Err(e) => {
+                self.log_str(format!("Error: {}", e));
+                return None;
+            }
+        };
+        let config = std::mem::replace(&mut self.config, project_config);
+        let counts = self.convert_source(source, output);
+        self.config = config;
+        counts
+    }
+
+    /// Converts `source` with the settings of its taiko.json, if it has one, on top of the
+    /// config in use. `run_conversion` swaps those settings in before calling it.
+    fn convert_source(&mut self, source: &str, output: &str) -> Option<Vec<(String, usize)>> {
+        match self.output_type {
+            ComboBoxConversion::SingleOutput => {
+                match MidiConverter::from_file(source)
+                    .and_then(|c| c.with_config(&self.config).convert_merged())
+                {
+                    Ok((r, report)) => {
+                        self.log_report(&report);
+                        let mut path_buf = PathBuf::new();
+                        path_buf.push(output);
+                        path_buf.push(format!(
+                            "{}{}",
+                            self.difficulty, &self.config.batch_output_extension
+                        ));
+                        self.write_dat_app(
+                            &path_buf.into_os_string().into_string().unwrap_or_default(),
+                            &r,
+                        );
+                        Some(vec![(self.difficulty.to_string(), r.notes.len())])
+                    }
+                    Err(e) => {
+                        self.log_str(format!("Error: {}", e));
+                        None
+                    }
+                }
+            }
+            ComboBoxConversion::MultiOutput => {
+                match MidiConverter::from_file(source)
+                    .and_then(|c| c.with_config(&self.config).convert())
+                {
+                    Ok(conversion) => {
+                        self.log_report(&conversion.report);
+                        let r = conversion.difficulties;
+                        for res in r.iter() {
+                            let mut path_buf = PathBuf::new();
+                            path_buf.push(output);
+                            path_buf
+                                .push(format!("{}{}", &res.0, &self.config.batch_output_extension));
+                            self.write_dat_app(
+                                &path_buf.into_os_string().into_string().unwrap_or_default(),
+                                &res.1,
+                            );
+                        }
 
-        match file_io::get_or_create_file_rw(&path) {
-            Ok(mut file) => {
-                let mut buffer = String::new();
-                file.read_to_string(&mut buffer).unwrap_or_default();
-                match serde_json::from_str(buffer.as_str()) {
-                    Ok(config) => config,
-                    Err(_) => {
-                        file_io::save_config(&Config::default(), &mut file);
-                        eprintln!("Failed to read config. Using default.");
-                        return Config::default();
+                        let mut song = self.song.clone();
+                        if song.song_name.is_empty() {
+                            song.song_name = song_name_from_source(source);
+                        }
+                        let info = edda_info::Root::from_difficulties(
+                            &song,
+                            &r,
+                            &self.config.batch_output_extension,
+                        );
+                        let mut path_buf = PathBuf::new();
+                        path_buf.push(output);
+                        path_buf.push("info.dat");
+                        self.write_info_app(
+                            &path_buf.into_os_string().into_string().unwrap_or_default(),
+                            &info,
+                        );
+                        Some(watch::note_counts(&r))
+                    }
+                    Err(e) => {
+                        self.log_str(format!("Error: {}", e));
+                        None
                     }
                 }
             }
-            Err(_) => {
-                eprintln!("Failed to read config. Using default.");
-                return Config::default();
+            ComboBoxConversion::Package => {
+                self.write_package_app(source, output);
+                None
+            }
+            ComboBoxConversion::Osu => {
+                self.write_osu_app(source, output);
+                None
+            }
+            ComboBoxConversion::Stepmania => {
+                self.write_stepmania_app(source, output);
+                None
+            }
+        }
+    }
+
+    fn write_package_app(&mut self, source: &str, output: &str) {
+        let (Some(audio), Some(cover)) = (self.audio_path.clone(), self.cover_path.clone()) else {
+            self.log_str("Error: Select an audio and a cover file to package.".to_string());
+            return;
+        };
+
+        match MidiConverter::from_file(source).and_then(|c| c.with_config(&self.config).convert()) {
+            Ok(conversion) => {
+                self.log_report(&conversion.report);
+                let r = conversion.difficulties;
+                let mut song = self.song.clone();
+                if song.song_name.is_empty() {
+                    song.song_name = song_name_from_source(source);
+                }
+                let package =
+                    SongPackage::new(&song, r, &self.config.batch_output_extension, audio, cover)
+                        .with_backups(self.config.backup_generations);
+                let written = if self.package_zip {
+                    package.write_zip(Path::new(output))
+                } else {
+                    package.write_folder(Path::new(output))
+                };
+                match written {
+                    Ok(path) => self.log_str(format!("Success! Wrote to: {}", path.display())),
+                    Err(e) => self.log_str(format!("Error: {}", e)),
+                }
+            }
+            Err(e) => self.log_str(format!("Error: {}", e)),
+        }
+    }
+
+    fn write_osu_app(&mut self, source: &str, output: &str) {
+        match MidiConverter::from_file(source).and_then(|c| c.with_config(&self.config).convert()) {
+            Ok(conversion) => {
+                self.log_report(&conversion.report);
+                let mut song = self.song.clone();
+                if song.song_name.is_empty() {
+                    song.song_name = song_name_from_source(source);
+                }
+                for (name, root) in conversion.difficulties.iter() {
+                    let osu = OsuBeatmap::new(&song, name, root).with_config(&self.config);
+                    let path = Path::new(output).join(osu.file_name());
+                    match file_io::write_output_bytes(
+                        &path.to_string_lossy().to_string(),
+                        osu.to_osu_string().as_bytes(),
+                    ) {
+                        Ok(_) => self.log_str(format!("Success! Wrote to: {}", path.display())),
+                        Err(e) => self.log_str(format!("Error: {}", e)),
+                    }
+                }
             }
+            Err(e) => self.log_str(format!("Error: {}", e)),
         }
     }
+
+    fn write_stepmania_app(&mut self, source: &str, output: &str) {
+        match MidiConverter::from_file(source).and_then(|c| c.with_config(&self.config).convert()) {
+            Ok(conversion) => {
+                self.log_report(&conversion.report);
+                let mut song = self.song.clone();
+                if song.song_name.is_empty() {
+                    song.song_name = song_name_from_source(source);
+                }
+                let sm = SmExporter::new(&song, &conversion.difficulties);
+                for (name, dropped) in sm.dropped_notes() {
+                    self.log_str(format!(
+                        "{} notes of {} are in lanes StepMania has no panel for and were left out",
+                        dropped, name
+                    ));
+                }
+                let path = Path::new(output).join(sm.file_name());
+                match file_io::write_output_bytes(
+                    &path.to_string_lossy().to_string(),
+                    sm.to_sm_string().as_bytes(),
+                ) {
+                    Ok(_) => self.log_str(format!("Success! Wrote to: {}", path.display())),
+                    Err(e) => self.log_str(format!("Error: {}", e)),
+                }
+            }
+            Err(e) => self.log_str(format!("Error: {}", e)),
+        }
+    }
+
+    /// Reads the profiles from the config file, creating it with the default profile if it
+    /// does not exist.
+    pub fn get_or_create_config(location: &ConfigLocation) -> Result<ProfileStore, TaikoError> {
+        let path = location.path.as_path();
+        // Earlier versions kept config.json in the working directory.
+        let local = Path::new("config.json");
+        if !location.forced && !path.exists() && local.is_file() && path != local {
+            if let Some(folder) = path.parent() {
+                let _ = fs::create_dir_all(folder);
+            }
+            match fs::copy(local, path) {
+                Ok(_) => eprintln!(
+                    "Copied config.json from the working directory to {}",
+                    path.display()
+                ),
+                Err(e) => eprintln!("Could not copy config.json to {}: {}", path.display(), e),
+            }
+        }
+
+        let profiles = file_io::read_config(path)?;
+        let is_empty = fs::metadata(path).map_or(true, |metadata| metadata.len() == 0);
+        if is_empty {
+            if let Err(e) = file_io::save_config(&profiles, path) {
+                eprintln!("Could not create the config: {}", e);
+            }
+        }
+        Ok(profiles)
+    }
 }
 
 impl eframe::App for TaikoApp {
@@ -365,35 +1076,497 @@ impl eframe::App for TaikoApp {
         self.show_configuration_panel(ctx);
         self.show_output(ctx);
         self.show_conversion_options(ctx);
+        self.watch_source(ctx);
+    }
+}
+
+fn song_name_from_source(source: &str) -> String {
+    Path::new(source)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Applies the input flags on top of the saved config.
+fn config_with_input(config: &Config, input: &InputArgs) -> Config {
+    let mut config = config.clone();
+    if input.rock_band {
+        config.input_format = InputFormat::RockBand;
+    }
+    if input.bpm.is_some() {
+        config.fallback_bpm = input.bpm;
+    }
+    if input.time_signature.is_some() {
+        config.fallback_time_signature = input.time_signature;
+    }
+    config
+}
+
+fn print_report(report: &ConversionReport) {
+    for line in report.lines() {
+        eprintln!("{}", line);
     }
 }
 
-pub fn handle_cli_input(args: Args, config: Config) {
+/// Writes a json file after backing up the version it replaces.
+fn write_json<T: serde::Serialize>(
+    path: &String,
+    data: &T,
+    config: &Config,
+    backup_since: Option<SystemTime>,
+) -> Result<(), TaikoError> {
+    back_up_output(path, config, backup_since)?;
+    println!("Writing to {} ...", path);
+    file_io::write_output_json(path, data)
+}
+
+/// Backs up the file at `path` before it is overwritten, once per watch session if
+/// `since` is set.
+fn back_up_output(
+    path: &String,
+    config: &Config,
+    since: Option<SystemTime>,
+) -> Result<(), TaikoError> {
+    match since {
+        Some(since) => backup::back_up_once(path, config.backup_generations, since)?,
+        None => backup::back_up(path, config.backup_generations)?,
+    };
+    Ok(())
+}
+
+fn write_bytes(path: &String, data: &[u8]) -> Result<(), TaikoError> {
+    println!("Writing to {} ...", path);
+    file_io::write_output_bytes(path, data)
+}
+
+/// Writes a .dat file, or updates the notes and bpm changes of an existing one.
+fn write_dat(
+    path: &String,
+    root: &Root,
+    output: &OutputArgs,
+    config: &Config,
+) -> Result<(), TaikoError> {
+    back_up_output(path, config, output.backup_since)?;
+    println!("Writing to {} ...", path);
+    if output.update {
+        file_io::update_output_json(path, root, output.beats)
+    } else {
+        file_io::write_output_json(path, root)
+    }
+}
+
+/// Writes every difficulty of `source` and an info.dat to `output_folder`, and returns the
+/// note count of each difficulty.
+fn write_auto(
+    source: &str,
+    output_folder: &str,
+    song: &SongMetadata,
+    config: &Config,
+    output: &OutputArgs,
+) -> Result<Vec<(String, usize)>, TaikoError> {
+    let conversion = MidiConverter::from_file(source)?
+        .with_config(config)
+        .convert()?;
+    print_report(&conversion.report);
+    write_difficulties(
+        &conversion.difficulties,
+        output_folder,
+        song,
+        config,
+        output,
+    )?;
+    Ok(watch::note_counts(&conversion.difficulties))
+}
+
+fn write_difficulties(
+    r: &[(String, Root)],
+    output_folder: &str,
+    song: &SongMetadata,
+    config: &Config,
+    output: &OutputArgs,
+) -> Result<(), TaikoError> {
+    for res in r.iter() {
+        let mut path_buf = PathBuf::new();
+        path_buf.push(output_folder);
+        path_buf.push(format!("{}{}", &res.0, &config.batch_output_extension));
+        write_dat(
+            &path_buf.into_os_string().into_string().unwrap_or_default(),
+            &res.1,
+            output,
+            config,
+        )?;
+    }
+
+    let info = edda_info::Root::from_difficulties(song, r, &config.batch_output_extension);
+    let mut path_buf = PathBuf::new();
+    path_buf.push(output_folder);
+    path_buf.push("info.dat");
+    let path = path_buf.into_os_string().into_string().unwrap_or_default();
+    back_up_output(&path, config, output.backup_since)?;
+    println!("Writing to {} ...", path);
+    file_io::update_info_json(&path, &info)
+}
+
+/// Converts one song of a batch into the folder of its midi file.
+fn convert_batch_song(
+    source: &Path,
+    config: &Config,
+    output: &OutputArgs,
+) -> Result<(Vec<(String, usize)>, ConversionReport), TaikoError> {
+    let conversion = MidiConverter::from_file(source)?
+        .with_config(config)
+        .convert()?;
+    let song = SongMetadata {
+        song_name: song_name_from_source(&source.to_string_lossy()),
+        ..Default::default()
+    };
+    let folder = source.parent().unwrap_or(Path::new("."));
+    write_difficulties(
+        &conversion.difficulties,
+        &folder.to_string_lossy(),
+        &song,
+        config,
+        output,
+    )?;
+    Ok((
+        watch::note_counts(&conversion.difficulties),
+        conversion.report,
+    ))
+}
+
+/// `config` with the taiko.json of `source` applied, noting which file was used.
+fn config_for_source(
+    location: &ConfigLocation,
+    config: &Config,
+    source: impl AsRef<Path>,
+) -> Result<Config, TaikoError> {
+    let (config, path) = location.config_for_source(config, source)?;
+    if let Some(path) = path {
+        eprintln!("Using settings from {}", path.display());
+    }
+    Ok(config)
+}
+
+pub fn handle_cli_input(
+    args: Args,
+    mut profiles: ProfileStore,
+    location: &ConfigLocation,
+) -> Result<(), TaikoError> {
+    let (profile_name, saved_config) = match &args.profile {
+        Some(name) => {
+            let profile = profiles
+                .profile(name)
+                .ok_or_else(|| TaikoError::UnknownProfile(name.clone()))?;
+            (profile.name.clone(), profile.config.clone())
+        }
+        None => (profiles.active_profile.clone(), profiles.active().clone()),
+    };
+    let mut config = saved_config.clone();
+    if let Some(name) = args.preset {
+        let preset = config
+            .preset(&name)
+            .ok_or(TaikoError::UnknownPreset(name))?;
+        config.drum_map = preset.drum_map;
+    }
+
     match args.command {
         Commands::Convert {
             source,
             output_file,
-        } => match MidiConverter::new(source, &config).to_root_merge_notes_and_meta() {
-            Ok(r) => file_io::write_output_json(&output_file, &r),
-            Err(e) => eprintln!("Error: {}", e),
-        },
+            output,
+            input,
+        } => {
+            let config = config_with_input(&config_for_source(location, &config, &source)?, &input);
+            let (r, report) = MidiConverter::from_file(source)?
+                .with_config(&config)
+                .convert_merged()?;
+            print_report(&report);
+            write_dat(&output_file, &r, &output, &config)?;
+        }
         Commands::Auto {
             source,
             output_folder,
-        } => match MidiConverter::new(source, &config).to_root_merge_meta() {
-            Ok(r) => {
-                for res in r {
-                    let mut path_buf = PathBuf::new();
-                    path_buf.push(output_folder.clone());
-                    path_buf.push(format!("{}{}", &res.0, &config.batch_output_extension));
-                    file_io::write_output_json(
-                        &path_buf.into_os_string().into_string().unwrap_or_default(),
-                        &res.1,
-                    );
+            song,
+            output,
+            input,
+        } => {
+            let config = config_with_input(&config_for_source(location, &config, &source)?, &input);
+            let song = SongMetadata {
+                song_name: song
+                    .song_name
+                    .unwrap_or_else(|| song_name_from_source(&source)),
+                song_author_name: song.author,
+                song_filename: song.song_file,
+                cover_image_filename: song.cover_file,
+            };
+            write_auto(&source, &output_folder, &song, &config, &output)?;
+        }
+        Commands::Batch {
+            root_folder,
+            pattern,
+            jobs,
+            output,
+            input,
+        } => {
+            let pattern = pattern.unwrap_or_else(|| config.batch_pattern.clone());
+            let sources = batch::find_sources(&root_folder, &pattern)?;
+            if sources.is_empty() {
+                println!("No files matching \"{}\" in {}", pattern, root_folder);
+                return Ok(());
+            }
+
+            let jobs = jobs.unwrap_or_else(|| {
+                std::thread::available_parallelism().map_or(1, |jobs| jobs.get())
+            });
+            let results = batch::run_parallel(&sources, jobs, |source| {
+                let config =
+                    config_with_input(&config_for_source(location, &config, source)?, &input);
+                convert_batch_song(source, &config, &output)
+            });
+
+            let names: Vec<String> = sources
+                .iter()
+                .map(|source| {
+                    source
+                        .strip_prefix(&root_folder)
+                        .unwrap_or(source)
+                        .display()
+                        .to_string()
+                })
+                .collect();
+            // Reports are printed after the run so the songs' lines do not interleave.
+            for (name, result) in names.iter().zip(results.iter()) {
+                if let Ok((_, report)) = result {
+                    for line in report.lines() {
+                        eprintln!("{}: {}", name, line);
+                    }
                 }
             }
-            Err(e) => eprintln!("Error: {}", e),
-        },
+
+            let width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+            println!("{:<width$}  Result", "Song", width = width);
+            let mut failed = 0;
+            for (name, result) in names.iter().zip(results.iter()) {
+                let status = match result {
+                    Ok((counts, _)) => {
+                        let counts: Vec<String> = counts
+                            .iter()
+                            .map(|(difficulty, notes)| format!("{} {}", difficulty, notes))
+                            .collect();
+                        format!("ok ({})", counts.join(", "))
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        format!("failed: {}", e)
+                    }
+                };
+                println!("{:<width$}  {}", name, status, width = width);
+            }
+
+            if failed > 0 {
+                return Err(TaikoError::BatchFailed {
+                    failed,
+                    total: sources.len(),
+                });
+            }
+        }
+        Commands::Watch {
+            source,
+            output_folder,
+            debounce,
+            song,
+            output,
+            input,
+        } => {
+            let song = SongMetadata {
+                song_name: song
+                    .song_name
+                    .unwrap_or_else(|| song_name_from_source(&source)),
+                song_author_name: song.author,
+                song_filename: song.song_file,
+                cover_image_filename: song.cover_file,
+            };
+            let mut watcher = SourceWatcher::new(&source, Duration::from_millis(debounce));
+            let output = OutputArgs {
+                backup_since: Some(watcher.started()),
+                ..output
+            };
+            // taiko.json is read again on every run, so its edits apply to the next save.
+            let convert = || {
+                let config =
+                    config_with_input(&config_for_source(location, &config, &source)?, &input);
+                write_auto(&source, &output_folder, &song, &config, &output)
+            };
+            let mut counts = convert()?;
+            println!("Watching {} for changes. Press Ctrl+C to stop.", source);
+            loop {
+                std::thread::sleep(watch::POLL_INTERVAL);
+                if !watcher.poll() {
+                    continue;
+                }
+                println!("{} changed, converting again ...", source);
+                // A broken export should not end the session; the next save may fix it.
+                match convert() {
+                    Ok(next) => {
+                        for line in watch::count_changes(&counts, &next) {
+                            println!("{}", line);
+                        }
+                        counts = next;
+                    }
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+            }
+        }
+        Commands::Package {
+            source,
+            output_folder,
+            audio,
+            cover,
+            song_name,
+            author,
+            zip,
+            input,
+        } => {
+            let config = config_with_input(&config_for_source(location, &config, &source)?, &input);
+            let conversion = MidiConverter::from_file(&source)?
+                .with_config(&config)
+                .convert()?;
+            print_report(&conversion.report);
+            let r = conversion.difficulties;
+            let song = SongMetadata {
+                song_name: song_name.unwrap_or_else(|| song_name_from_source(&source)),
+                song_author_name: author,
+                ..Default::default()
+            };
+            let package = SongPackage::new(&song, r, &config.batch_output_extension, audio, cover)
+                .with_backups(config.backup_generations);
+            let path = if zip {
+                package.write_zip(Path::new(&output_folder))?
+            } else {
+                package.write_folder(Path::new(&output_folder))?
+            };
+            println!("Packaged song to {}", path.display());
+        }
+        Commands::Osu {
+            source,
+            output_folder,
+            song,
+            input,
+        } => {
+            let config = config_with_input(&config_for_source(location, &config, &source)?, &input);
+            let conversion = MidiConverter::from_file(&source)?
+                .with_config(&config)
+                .convert()?;
+            print_report(&conversion.report);
+
+            let song = SongMetadata {
+                song_name: song
+                    .song_name
+                    .unwrap_or_else(|| song_name_from_source(&source)),
+                song_author_name: song.author,
+                song_filename: song.song_file,
+                cover_image_filename: song.cover_file,
+            };
+            for (name, root) in conversion.difficulties.iter() {
+                let osu = OsuBeatmap::new(&song, name, root).with_config(&config);
+                let path = Path::new(&output_folder).join(osu.file_name());
+                write_bytes(
+                    &path.to_string_lossy().to_string(),
+                    osu.to_osu_string().as_bytes(),
+                )?;
+            }
+        }
+        Commands::Sm {
+            source,
+            output_folder,
+            song,
+            input,
+        } => {
+            let config = config_with_input(&config_for_source(location, &config, &source)?, &input);
+            let conversion = MidiConverter::from_file(&source)?
+                .with_config(&config)
+                .convert()?;
+            print_report(&conversion.report);
+
+            let song = SongMetadata {
+                song_name: song
+                    .song_name
+                    .unwrap_or_else(|| song_name_from_source(&source)),
+                song_author_name: song.author,
+                song_filename: song.song_file,
+                cover_image_filename: song.cover_file,
+            };
+            let sm = SmExporter::new(&song, &conversion.difficulties);
+            for (name, dropped) in sm.dropped_notes() {
+                println!(
+                    "{} notes of {} are in lanes StepMania has no panel for and were left out",
+                    dropped, name
+                );
+            }
+            let path = Path::new(&output_folder).join(sm.file_name());
+            write_bytes(
+                &path.to_string_lossy().to_string(),
+                sm.to_sm_string().as_bytes(),
+            )?;
+        }
+        Commands::Sm2dat {
+            source,
+            output_folder,
+        } => {
+            let config = config_for_source(location, &config, &source)?;
+            let imported = SmImporter::from_file(&source)?
+                .with_config(&config)
+                .import()?;
+            for (name, root) in imported.difficulties.iter() {
+                let path = Path::new(&output_folder)
+                    .join(format!("{}{}", name, &config.batch_output_extension));
+                write_json(&path.to_string_lossy().to_string(), root, &config, None)?;
+            }
+
+            let info = edda_info::Root::from_difficulties(
+                &imported.song,
+                &imported.difficulties,
+                &config.batch_output_extension,
+            );
+            let path = Path::new(&output_folder).join("info.dat");
+            write_json(&path.to_string_lossy().to_string(), &info, &config, None)?;
+        }
+        Commands::Dat2midi {
+            source,
+            output_file,
+        } => {
+            let config = config_for_source(location, &config, &source)?;
+            let conversion = DatConverter::from_file(source)?
+                .with_config(&config)
+                .convert()?;
+            for note in conversion.skipped.iter() {
+                eprintln!("Skipping {}", note);
+            }
+            write_bytes(&output_file, &conversion.midi)?;
+        }
+        Commands::Restore { file, number, list } => {
+            let backups = backup::backups(&file)?;
+            if list {
+                if backups.is_empty() {
+                    println!("{} has no backups", file.display());
+                }
+                for (i, backup) in backups.iter().enumerate() {
+                    println!("{}: {}", i + 1, backup.time());
+                }
+                return Ok(());
+            }
+
+            let backup = number
+                .checked_sub(1)
+                .and_then(|i| backups.get(i))
+                .ok_or(TaikoError::BackupNotFound { path: file, number })?;
+            println!(
+                "Restoring {} from {} ...",
+                backup.original.display(),
+                backup.time()
+            );
+            backup.restore(config.backup_generations)?;
+        }
         Commands::Configure => {
             println!("Midi Pitch Config:");
 
@@ -401,15 +1574,15 @@ pub fn handle_cli_input(args: Args, config: Config) {
             let mut batch_extension = config.batch_output_extension.clone();
 
             for (i, map_val) in map.iter_mut().enumerate() {
-                let mut buf = String::new();
                 loop {
                     print!(
-                        "Position [{}] is currently set to <{}>. Please enter the new value: ",
+                        "Lane [{}] is currently set to <{}>. Please enter the new pitches (e.g. 38, 40, 35-36): ",
                         i, map_val
                     );
                     stdout().flush().unwrap();
+                    let mut buf = String::new();
                     match io::stdin().read_line(&mut buf) {
-                        Ok(_) => match buf.trim().parse::<u8>() {
+                        Ok(_) => match buf.trim().parse::<LanePitches>() {
                             Ok(parsed) => {
                                 *map_val = parsed;
                                 break;
@@ -448,16 +1621,131 @@ pub fn handle_cli_input(args: Args, config: Config) {
             let new_config = Config {
                 drum_map: map,
                 batch_output_extension: batch_extension,
+                ..config
+            };
+
+            profiles.set_config(&profile_name, new_config)?;
+            file_io::save_config(&profiles, &location.path)?;
+        }
+        Commands::SavePreset { name } => {
+            // Only the preset list is saved; a --preset pick does not replace the drum map.
+            config.save_preset(&name);
+            let new_config = Config {
+                drum_map_presets: config.drum_map_presets,
+                ..saved_config
             };
 
-            match file_io::get_or_create_file_rw(&Path::new("config.json")) {
-                Ok(mut file) => {
-                    file_io::save_config(&new_config, &mut file);
+            profiles.set_config(&profile_name, new_config)?;
+            file_io::save_config(&profiles, &location.path)?;
+            println!("Saved preset \"{}\"", name.trim());
+        }
+        Commands::Presets => {
+            for preset in config.presets() {
+                let lanes: Vec<String> = preset
+                    .drum_map
+                    .iter()
+                    .map(|lane| format!("[{}]", lane))
+                    .collect();
+                println!("{}: {}", preset.name, lanes.join(" "));
+            }
+        }
+        Commands::Profile { command } => {
+            match command {
+                ProfileCommands::List => {
+                    for profile in profiles.profiles.iter() {
+                        let marker = if profile.name == profiles.active_profile {
+                            "*"
+                        } else {
+                            " "
+                        };
+                        println!("{} {}", marker, profile.name);
+                    }
+                    return Ok(());
                 }
-                Err(e) => {
-                    eprintln!("Could not save config: {}", e);
+                ProfileCommands::Create { name } => {
+                    profiles.create(&name, Config::default())?;
+                    println!("Created profile \"{}\"", name.trim());
                 }
-            };
+                ProfileCommands::Delete { name } => {
+                    profiles.delete(&name)?;
+                    println!("Deleted profile \"{}\"", name.trim());
+                }
+                ProfileCommands::Copy { from, to } => {
+                    profiles.copy(&from, &to)?;
+                    println!("Copied profile \"{}\" to \"{}\"", from.trim(), to.trim());
+                }
+            }
+
+            file_io::save_config(&profiles, &location.path)?;
         }
+        Commands::Config { command } => match command {
+            ConfigCommands::Path { source } => {
+                let kind = if location.forced {
+                    "picked with --config"
+                } else {
+                    "global config"
+                };
+                println!("{} ({})", location.path.display(), kind);
+                let source = source.unwrap_or_else(|| PathBuf::from("."));
+                if let Some(path) = location.project_file(&source) {
+                    println!("{} (project overrides)", path.display());
+                }
+            }
+            ConfigCommands::Get { key } => {
+                println!(
+                    "{}",
+                    saved_config
+                        .setting(&key)
+                        .map_err(TaikoError::InvalidSetting)?
+                );
+            }
+            ConfigCommands::Set { key, value } => {
+                let mut new_config = saved_config;
+                new_config
+                    .set_setting(&key, &value)
+                    .map_err(TaikoError::InvalidSetting)?;
+                let value = new_config
+                    .setting(&key)
+                    .map_err(TaikoError::InvalidSetting)?;
+                profiles.set_config(&profile_name, new_config)?;
+                file_io::save_config(&profiles, &location.path)?;
+                println!("{} = {}", key, value);
+            }
+            ConfigCommands::Reset { key } => {
+                let new_config = match &key {
+                    Some(key) => {
+                        let default = Config::default()
+                            .setting(key)
+                            .map_err(TaikoError::InvalidSetting)?;
+                        let mut new_config = saved_config;
+                        new_config
+                            .set_setting(key, &default)
+                            .map_err(TaikoError::InvalidSetting)?;
+                        new_config
+                    }
+                    None => Config::default(),
+                };
+                profiles.set_config(&profile_name, new_config)?;
+                file_io::save_config(&profiles, &location.path)?;
+                match key {
+                    Some(key) => println!("Reset {} in profile \"{}\"", key, profile_name),
+                    None => println!("Reset profile \"{}\" to the default settings", profile_name),
+                }
+            }
+            ConfigCommands::Show { json } => {
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&saved_config)?);
+                } else {
+                    for key in saved_config.setting_names() {
+                        let value = saved_config
+                            .setting(&key)
+                            .map_err(TaikoError::InvalidSetting)?;
+                        println!("{} = {}", key, value);
+                    }
+                }
+            }
+        },
     }
+
+    Ok(())
 }
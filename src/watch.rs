@@ -0,0 +1,132 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::json_structures::edda_objects::Root;
+
+/// Watches a source file for changes by polling its modification time and size.
+///
+/// A DAW usually writes an export in several steps, so a change is only reported once the
+/// file has stayed the same for the debounce interval. Saves that follow each other more
+/// quickly than that are reported as one change.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// let mut watcher = taiko::watch::SourceWatcher::new("song.mid", Duration::from_millis(500));
+/// loop {
+///     if watcher.poll() {
+///         // Reconvert the file.
+///     }
+///     std::thread::sleep(taiko::watch::POLL_INTERVAL);
+/// }
+/// ```
+pub struct SourceWatcher {
+    path: PathBuf,
+    debounce: Duration,
+    /// State of the file when it was last reported.
+    seen: Option<FileState>,
+    /// A state that differs from `seen`, and since when the file has had it.
+    pending: Option<(FileState, Instant)>,
+    started: SystemTime,
+}
+
+/// How often callers are expected to poll a [`SourceWatcher`].
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Debounce interval used when none is configured.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl SourceWatcher {
+    /// Starts watching `path`. Its current state counts as seen.
+    pub fn new(path: impl AsRef<Path>, debounce: Duration) -> Self {
+        let path = path.as_ref().to_path_buf();
+        SourceWatcher {
+            seen: SourceWatcher::state(&path),
+            path,
+            debounce,
+            pending: None,
+            started: SystemTime::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// When watching started.
+    pub fn started(&self) -> SystemTime {
+        self.started
+    }
+
+    /// Returns true once the file has changed and then stayed unchanged for the debounce
+    /// interval. A missing file, e.g. while a DAW replaces it, is not a change.
+    pub fn poll(&mut self) -> bool {
+        let Some(state) = SourceWatcher::state(&self.path) else {
+            self.pending = None;
+            return false;
+        };
+        if self.seen == Some(state) {
+            self.pending = None;
+            return false;
+        }
+
+        match self.pending {
+            Some((pending, since)) if pending == state => {
+                if since.elapsed() < self.debounce {
+                    return false;
+                }
+                self.seen = Some(state);
+                self.pending = None;
+                true
+            }
+            _ => {
+                self.pending = Some((state, Instant::now()));
+                false
+            }
+        }
+    }
+
+    fn state(path: &Path) -> Option<FileState> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(FileState {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// The number of notes of every difficulty.
+pub fn note_counts(difficulties: &[(String, Root)]) -> Vec<(String, usize)> {
+    difficulties
+        .iter()
+        .map(|(name, root)| (name.clone(), root.notes.len()))
+        .collect()
+}
+
+/// Describes how the note counts of a conversion changed since the previous one, one line
+/// per difficulty, e.g. "Hard: 212 notes (+4)".
+pub fn count_changes(before: &[(String, usize)], after: &[(String, usize)]) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    for (name, count) in after {
+        let change = match before.iter().find(|(previous, _)| previous == name) {
+            None => "new".to_string(),
+            Some((_, previous)) if previous == count => "unchanged".to_string(),
+            Some((_, previous)) => format!("{:+}", *count as i64 - *previous as i64),
+        };
+        lines.push(format!("{}: {} notes ({})", name, count, change));
+    }
+    for (name, _) in before {
+        if !after.iter().any(|(next, _)| next == name) {
+            lines.push(format!("{}: removed", name));
+        }
+    }
+
+    lines
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::{
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use common::folder;
+use taiko::backup::{back_up, back_up_once, backups, folder_backups, BACKUP_FOLDER};
+
+/// Writes `contents` to `path` after backing up the previous version, like a conversion.
+fn save(path: &PathBuf, contents: &str, generations: usize) {
+    back_up(path, generations).unwrap();
+    fs::write(path, contents).unwrap();
+    // Backups are named to the millisecond.
+    thread::sleep(Duration::from_millis(3));
+}
+
+#[test]
+fn keeps_the_newest_generations() {
+    let folder = folder("backup-generations");
+    let path = folder.join("Hard.dat");
+    for version in ["one", "two", "three", "four", "five"] {
+        save(&path, version, 3);
+    }
+
+    let kept: Vec<String> = backups(&path)
+        .unwrap()
+        .iter()
+        .map(|backup| fs::read_to_string(&backup.path).unwrap())
+        .collect();
+    let stored = fs::read_dir(folder.join(BACKUP_FOLDER)).unwrap().count();
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(kept, vec!["four", "three", "two"]);
+    assert_eq!(stored, 3);
+}
+
+#[test]
+fn backs_up_once_per_watch_session() {
+    let folder = folder("backup-once");
+    let path = folder.join("Hard.dat");
+    save(&path, "before", 10);
+    save(&path, "original", 10);
+
+    let session = SystemTime::now();
+    thread::sleep(Duration::from_millis(3));
+    for version in ["one", "two", "three"] {
+        back_up_once(&path, 10, session).unwrap();
+        fs::write(&path, version).unwrap();
+        thread::sleep(Duration::from_millis(3));
+    }
+
+    let kept: Vec<String> = backups(&path)
+        .unwrap()
+        .iter()
+        .map(|backup| fs::read_to_string(&backup.path).unwrap())
+        .collect();
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(kept, ["original", "before"]);
+}
+
+#[test]
+fn tells_files_with_similar_names_apart() {
+    let folder = folder("backup-names");
+    for name in ["info.dat", "Hard.dat", "My.Song.dat", "Hard"] {
+        let path = folder.join(name);
+        save(&path, "old", 10);
+        save(&path, "new", 10);
+    }
+
+    let listed: Vec<String> = folder_backups(&folder)
+        .unwrap()
+        .iter()
+        .map(|backup| {
+            let name = backup.original.file_name().unwrap().to_string_lossy();
+            format!("{} {}", name, backup.time().len())
+        })
+        .collect();
+    let hard = backups(folder.join("Hard.dat")).unwrap();
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(
+        listed,
+        vec!["Hard 23", "Hard.dat 23", "My.Song.dat 23", "info.dat 23"]
+    );
+    assert_eq!(hard.len(), 1);
+    assert!(hard[0].path.to_string_lossy().ends_with(".dat"));
+}
+
+#[test]
+fn restores_a_backup_and_can_undo_it() {
+    let folder = folder("backup-restore");
+    let path = folder.join("Normal.dat");
+    save(&path, "edited in Edda", 2);
+    save(&path, "converted", 2);
+    save(&path, "converted again", 2);
+
+    // The oldest backup survives the extra backup taken by restoring it.
+    let oldest = backups(&path).unwrap().pop().unwrap();
+    oldest.restore(2).unwrap();
+    let restored = fs::read_to_string(&path).unwrap();
+    thread::sleep(Duration::from_millis(3));
+    backups(&path).unwrap()[0].restore(2).unwrap();
+    let undone = fs::read_to_string(&path).unwrap();
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(restored, "edited in Edda");
+    assert_eq!(undone, "converted again");
+}
+
+#[test]
+fn keeps_backups_taken_within_the_same_millisecond() {
+    let folder = folder("backup-same-time");
+    let path = folder.join("Hard.dat");
+    for version in ["one", "two", "three", "four"] {
+        fs::write(&path, version).unwrap();
+        back_up(&path, 10).unwrap();
+    }
+
+    let kept: Vec<String> = backups(&path)
+        .unwrap()
+        .iter()
+        .map(|backup| fs::read_to_string(&backup.path).unwrap())
+        .collect();
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(kept, ["four", "three", "two", "one"]);
+}
+
+#[test]
+fn skips_missing_files_and_disabled_backups() {
+    let folder = folder("backup-skip");
+    let path = folder.join("Easy.dat");
+
+    assert_eq!(back_up(&path, 10).unwrap(), None);
+    fs::write(&path, "map").unwrap();
+    assert_eq!(back_up(&path, 0).unwrap(), None);
+    let backed_up = back_up(&path, 10).unwrap().unwrap();
+    let contents = fs::read_to_string(&backed_up.path).unwrap();
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(backed_up.original, path);
+    assert_eq!(contents, "map");
+}
@@ -0,0 +1,71 @@
+use taiko::batch::{find_sources, matches_pattern, run_parallel};
+
+#[test]
+fn matches_wildcards_ignoring_case() {
+    assert!(matches_pattern("*.mid", "Song.MID"));
+    assert!(matches_pattern("*.mid", ".mid"));
+    assert!(matches_pattern("drums*.mid", "drums - final.mid"));
+    assert!(matches_pattern("song?.mid", "song2.mid"));
+    assert!(matches_pattern("*a*b*", "xaxxbx"));
+    assert!(!matches_pattern("*.mid", "song.midi"));
+    assert!(!matches_pattern("song?.mid", "song.mid"));
+    assert!(!matches_pattern("drums*.mid", "song.mid"));
+}
+
+#[test]
+fn finds_matching_files_in_every_folder() {
+    let root = std::env::temp_dir().join(format!("taiko-batch-{}", std::process::id()));
+    for folder in ["b", "a/nested", "c"] {
+        std::fs::create_dir_all(root.join(folder)).unwrap();
+    }
+    for file in [
+        "b/b.mid",
+        "a/nested/a.MID",
+        "a/Hard.dat",
+        "c/notes.txt",
+        "top.mid",
+    ] {
+        std::fs::write(root.join(file), b"").unwrap();
+    }
+
+    let found = find_sources(&root, "*.mid").unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    let found: Vec<String> = found
+        .iter()
+        .map(|path| {
+            path.strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+    assert_eq!(found, vec!["a/nested/a.MID", "b/b.mid", "top.mid"]);
+}
+
+#[test]
+fn keeps_results_in_order() {
+    let items: Vec<u64> = (0..20).collect();
+    let results = run_parallel(&items, 4, |&item| {
+        std::thread::sleep(std::time::Duration::from_millis(20 - item));
+        item * 2
+    });
+
+    assert_eq!(results, (0..20).map(|item| item * 2).collect::<Vec<_>>());
+    assert!(run_parallel(&Vec::<u64>::new(), 4, |&item| item).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn does_not_follow_symlinked_folders() {
+    let root = std::env::temp_dir().join(format!("taiko-batch-links-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("song")).unwrap();
+    std::fs::write(root.join("song/song.mid"), b"").unwrap();
+    // A link back to the root would otherwise be walked forever.
+    std::os::unix::fs::symlink(&root, root.join("song/loop")).unwrap();
+
+    let found = find_sources(&root, "*.mid").unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(found, vec![root.join("song/song.mid")]);
+}
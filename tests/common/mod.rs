@@ -0,0 +1,130 @@
+//! Builds small midi files in memory for the conversion tests, and temporary folders for
+//! the tests that write files.
+#![allow(dead_code)]
+
+use std::{fs, path::PathBuf};
+
+use midly::{
+    num::{u15, u24, u28, u4, u7},
+    Format, Fps, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+};
+
+pub enum Event {
+    Tempo(u32),
+    TimeSignature(u8, u8),
+    Note(u8),
+    /// A note with a velocity other than 100.
+    NoteWithVelocity(u8, u8),
+}
+
+/// A format 1 file with a meta track holding `meta` and one note track named `name`.
+pub fn midi_file(
+    timing: Timing,
+    meta: &[(u64, Event)],
+    name: &str,
+    notes: &[(u64, Event)],
+) -> Vec<u8> {
+    midi_file_with_tracks(timing, meta, &[(name, notes)])
+}
+
+/// A format 1 file with a meta track holding `meta` and a note track for every entry.
+pub fn midi_file_with_tracks(
+    timing: Timing,
+    meta: &[(u64, Event)],
+    tracks: &[(&str, &[(u64, Event)])],
+) -> Vec<u8> {
+    let mut smf = Smf {
+        header: Header::new(Format::Parallel, timing),
+        tracks: vec![track(meta, None)],
+    };
+    for (name, notes) in tracks {
+        smf.tracks.push(track(notes, Some(name.as_bytes())));
+    }
+
+    let mut bytes = Vec::new();
+    smf.write(&mut bytes).unwrap();
+    bytes
+}
+
+pub fn metrical(ppq: u16) -> Timing {
+    Timing::Metrical(u15::new(ppq))
+}
+
+pub fn timecode(fps: Fps, subframes: u8) -> Timing {
+    Timing::Timecode(fps, subframes)
+}
+
+fn track<'a>(events: &[(u64, Event)], name: Option<&'a [u8]>) -> Vec<TrackEvent<'a>> {
+    let mut stamped = Vec::<(u64, TrackEventKind)>::new();
+    if let Some(name) = name {
+        stamped.push((0, TrackEventKind::Meta(MetaMessage::TrackName(name))));
+    }
+    for (ticks, event) in events {
+        match event {
+            Event::Tempo(us) => stamped.push((
+                *ticks,
+                TrackEventKind::Meta(MetaMessage::Tempo(u24::new(*us))),
+            )),
+            Event::TimeSignature(numerator, denominator) => stamped.push((
+                *ticks,
+                TrackEventKind::Meta(MetaMessage::TimeSignature(
+                    *numerator,
+                    denominator.trailing_zeros() as u8,
+                    24,
+                    8,
+                )),
+            )),
+            Event::Note(key) | Event::NoteWithVelocity(key, _) => {
+                let velocity = match event {
+                    Event::NoteWithVelocity(_, velocity) => *velocity,
+                    _ => 100,
+                };
+                let channel = u4::new(9);
+                stamped.push((
+                    *ticks,
+                    TrackEventKind::Midi {
+                        channel,
+                        message: MidiMessage::NoteOn {
+                            key: u7::new(*key),
+                            vel: u7::new(velocity),
+                        },
+                    },
+                ));
+                stamped.push((
+                    *ticks + 1,
+                    TrackEventKind::Midi {
+                        channel,
+                        message: MidiMessage::NoteOff {
+                            key: u7::new(*key),
+                            vel: u7::new(0),
+                        },
+                    },
+                ));
+            }
+        }
+    }
+    stamped.sort_by_key(|e| e.0);
+
+    let mut last = 0;
+    let mut track = Vec::new();
+    for (ticks, kind) in stamped {
+        track.push(TrackEvent {
+            delta: u28::new((ticks - last) as u32),
+            kind,
+        });
+        last = ticks;
+    }
+    track.push(TrackEvent {
+        delta: u28::new(0),
+        kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+    });
+    track
+}
+
+/// An empty folder named after `name` in the temp directory, unique to this test run.
+pub fn folder(name: &str) -> PathBuf {
+    let folder = std::env::temp_dir().join(format!("taiko-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&folder);
+    fs::create_dir_all(&folder).unwrap();
+    folder
+}
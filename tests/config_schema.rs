@@ -0,0 +1,76 @@
+mod common;
+
+use std::fs;
+
+use common::folder;
+use serde_json::{json, Value};
+use taiko::{
+    backup::backups,
+    file_io,
+    json_structures::custom::{migrate_config, ProfileStore},
+    TaikoError,
+};
+
+#[test]
+fn migrates_every_older_layout() {
+    let single = json!({"drum_map": [36, 38, 42, 49], "batch_output_extension": ".dat"});
+    let profiles = json!({"active_profile": "Default", "profiles": [
+        {"name": "Default", "drum_map": [36, 38, 42, 49], "batch_output_extension": ".dat"}
+    ]});
+
+    let from_single = migrate_config(single).unwrap();
+    let from_profiles = migrate_config(profiles).unwrap();
+
+    assert_eq!(from_single, from_profiles);
+    assert_eq!(from_single["version"], json!(ProfileStore::VERSION));
+    assert_eq!(from_single["profiles"][0]["drum_map"][2], json!(42));
+}
+
+#[test]
+fn keeps_unknown_and_fills_in_missing_settings() {
+    let json = r#"{"version": 1, "active_profile": "Live", "window": {"width": 800},
+        "profiles": [{"name": "Live", "drum_map": [1, 2, 3, 4], "lane_colours": ["red"]}]}"#;
+    let profiles: ProfileStore = serde_json::from_str(json).unwrap();
+    let saved: Value = serde_json::to_value(&profiles).unwrap();
+
+    assert_eq!(profiles.active().batch_output_extension, ".dat");
+    assert_eq!(profiles.active().lane_for_pitch(3), Some(2));
+    assert_eq!(saved["window"], json!({"width": 800}));
+    assert_eq!(saved["profiles"][0]["lane_colours"], json!(["red"]));
+    assert_eq!(saved["version"], json!(ProfileStore::VERSION));
+}
+
+#[test]
+fn refuses_configs_from_newer_versions() {
+    let newer = json!({"version": ProfileStore::VERSION + 1, "profiles": []});
+
+    assert!(migrate_config(newer).is_err());
+    assert!(migrate_config(json!({"version": "two"})).is_err());
+}
+
+#[test]
+fn backs_up_a_broken_config_and_leaves_it_alone() {
+    let folder = folder("config-broken");
+    let path = folder.join("config.json");
+    let broken = r#"{"drum_map": [36, 38, 42, 49],}"#;
+    fs::write(&path, broken).unwrap();
+
+    let first = file_io::read_config(&path);
+    let second = file_io::read_config(&path);
+    let contents = fs::read_to_string(&path).unwrap();
+    let kept = backups(&path).unwrap();
+    let copy = fs::read_to_string(&kept[0].path).unwrap();
+    let missing = file_io::read_config(&folder.join("missing.json"));
+    fs::remove_dir_all(&folder).unwrap();
+
+    let Err(TaikoError::InvalidConfig { backup, .. }) = first else {
+        panic!("expected an invalid config error");
+    };
+    assert!(matches!(second, Err(TaikoError::InvalidConfig { .. })));
+    assert_eq!(backup, Some(kept[0].path.clone()));
+    // Reading it again does not take another backup of the same file.
+    assert_eq!(kept.len(), 1);
+    assert_eq!(contents, broken);
+    assert_eq!(copy, broken);
+    assert_eq!(missing.unwrap(), ProfileStore::default());
+}
@@ -0,0 +1,80 @@
+use taiko::json_structures::custom::{Config, OsuHit, TimeSignature, UnmappedNotePolicy};
+
+#[test]
+fn sets_settings_from_command_line_text() {
+    let mut config = Config::default();
+
+    config.set_setting("drum_map", "36,38,45,49").unwrap();
+    assert_eq!(config.setting("drum_map").unwrap(), "36; 38; 45; 49");
+    config
+        .set_setting("drum_map", "36; 38, 40; 45-47; 49")
+        .unwrap();
+    assert_eq!(config.lane_for_pitch(46), Some(2));
+    assert_eq!(config.lane_for_pitch(40), Some(1));
+
+    config
+        .set_setting("batch_output_extension", ".json")
+        .unwrap();
+    config.set_setting("unmapped_notes", "Nearest").unwrap();
+    config.set_setting("fallback_bpm", "120").unwrap();
+    config
+        .set_setting("fallback_time_signature", "6/8")
+        .unwrap();
+    config
+        .set_setting("osu_hits", "Kat, Don, Don, BigKat")
+        .unwrap();
+
+    assert_eq!(config.batch_output_extension, ".json");
+    assert_eq!(config.unmapped_notes, UnmappedNotePolicy::Nearest);
+    assert_eq!(config.fallback_bpm, Some(120.0));
+    assert_eq!(
+        config.fallback_time_signature,
+        Some(TimeSignature {
+            numerator: 6,
+            denominator: 8
+        })
+    );
+    assert_eq!(config.osu_hits[3], OsuHit::BigKat);
+    assert_eq!(config.setting("fallback_time_signature").unwrap(), "6/8");
+    assert_eq!(config.setting("osu_hits").unwrap(), "Kat, Don, Don, BigKat");
+
+    config.set_setting("fallback_bpm", "null").unwrap();
+    assert_eq!(config.fallback_bpm, None);
+}
+
+#[test]
+fn checks_settings_like_the_gui() {
+    let mut config = Config::default();
+
+    assert!(config.set_setting("drum_map", "36,128,45,49").is_err());
+    assert!(config.set_setting("drum_map", "36,40-38,45,49").is_err());
+    assert!(config.set_setting("drum_map", "").is_err());
+    assert!(config.set_setting("drum_map", "36,,38").is_err());
+    assert!(config.set_setting("drum_map", "36; ; 42").is_err());
+    assert!(config.set_setting("fallback_bpm", "0").is_err());
+    assert!(config.set_setting("fallback_bpm", "-5").is_err());
+    assert!(config.set_setting("fallback_bpm", "1000").is_err());
+    assert!(config.set_setting("time_precision", "400").is_err());
+    assert!(config.set_setting("time_precision", "six").is_err());
+    assert!(config.set_setting("unmapped_notes", "Sometimes").is_err());
+    assert!(config
+        .set_setting("fallback_time_signature", "5/7")
+        .is_err());
+    assert!(config.set_setting("drum_mapp", "36").is_err());
+    assert!(config.setting("drum_mapp").is_err());
+    assert_eq!(config, Config::default());
+}
+
+#[test]
+fn reads_back_every_setting_it_prints() {
+    let config = Config::default();
+    let mut copy = config.clone();
+    copy.set_setting("drum_map", "1,2,3,4").unwrap();
+
+    for key in config.setting_names() {
+        copy.set_setting(&key, &config.setting(&key).unwrap())
+            .unwrap();
+    }
+
+    assert_eq!(copy, config);
+}
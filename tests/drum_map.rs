@@ -0,0 +1,113 @@
+mod common;
+
+use common::{metrical, midi_file, Event};
+use taiko::{
+    json_structures::custom::{Config, LanePitches, UnmappedNotePolicy},
+    MidiConverter,
+};
+
+fn lanes(config: &Config, pitches: &[u8]) -> Vec<i64> {
+    let notes: Vec<(u64, Event)> = pitches
+        .iter()
+        .enumerate()
+        .map(|(i, &pitch)| (i as u64 * 480, Event::Note(pitch)))
+        .collect();
+    let bytes = midi_file(
+        metrical(480),
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        "Hard",
+        &notes,
+    );
+    let (root, _) = MidiConverter::from_bytes(bytes)
+        .with_config(config)
+        .convert_merged()
+        .unwrap();
+    root.notes.iter().map(|note| note.line_index).collect()
+}
+
+#[test]
+fn reads_configs_with_one_pitch_per_lane() {
+    let config: Config =
+        serde_json::from_str(r#"{"drum_map": [36, 38, 42, 49], "batch_output_extension": ".dat"}"#)
+            .unwrap();
+
+    assert_eq!(config.drum_map[1].to_string(), "38");
+    assert_eq!(config.lane_for_pitch(42), Some(2));
+    assert_eq!(config.lane_for_pitch(40), None);
+}
+
+#[test]
+fn round_trips_pitch_lists_and_ranges() {
+    let json = r#"{"drum_map": [[35, 36], [38, 40, "37-39"], "42-46", [49]], "batch_output_extension": ".dat"}"#;
+    let config: Config = serde_json::from_str(json).unwrap();
+    let saved = serde_json::to_string(&config).unwrap();
+
+    assert_eq!(config.drum_map[1].to_string(), "38, 40, 37-39");
+    assert_eq!(config.drum_map[2].to_string(), "42-46");
+    assert!(saved.contains(r#"[[35,36],[38,40,"37-39"],["42-46"],[49]]"#));
+    assert_eq!(serde_json::from_str::<Config>(&saved).unwrap(), config);
+}
+
+#[test]
+fn maps_several_pitches_to_one_lane() {
+    let config = Config {
+        drum_map: ["35, 36", "37-40", "42, 44, 46", "49, 57"]
+            .iter()
+            .map(|lane| lane.parse().unwrap())
+            .collect(),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        lanes(&config, &[36, 37, 38, 40, 44, 57]),
+        vec![0, 1, 1, 1, 2, 3]
+    );
+}
+
+#[test]
+fn moves_unmapped_notes_to_the_nearest_range() {
+    let config = Config {
+        drum_map: ["35-36", "37-40", "42-46", "49-57"]
+            .iter()
+            .map(|lane| lane.parse().unwrap())
+            .collect(),
+        unmapped_notes: UnmappedNotePolicy::Nearest,
+        ..Default::default()
+    };
+
+    assert_eq!(lanes(&config, &[30, 41, 47, 70]), vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn rejects_invalid_pitch_lists() {
+    assert!("38, 128".parse::<LanePitches>().is_err());
+    assert!("40-38".parse::<LanePitches>().is_err());
+    assert!("snare".parse::<LanePitches>().is_err());
+    assert_eq!("".parse::<LanePitches>(), Ok(LanePitches::default()));
+}
+
+#[test]
+fn nearest_reports_notes_it_cannot_move_as_skipped() {
+    let config = Config {
+        drum_map: Vec::new(),
+        unmapped_notes: UnmappedNotePolicy::Nearest,
+        ..Default::default()
+    };
+    let bytes = midi_file(
+        metrical(480),
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        "Hard",
+        &[(0, Event::Note(38))],
+    );
+    let (root, report) = MidiConverter::from_bytes(bytes)
+        .with_config(&config)
+        .convert_merged()
+        .unwrap();
+
+    assert!(root.notes.is_empty());
+    assert_eq!(report.unmapped[0].lane, None);
+    assert_eq!(
+        report.lines()[0],
+        "1 notes not in the drum map were skipped:"
+    );
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::{metrical, midi_file, Event};
+use taiko::{
+    json_structures::{
+        custom::{Config, TimeSignature},
+        edda_objects::Root,
+    },
+    ConversionReport, MidiConverter, TaikoError,
+};
+
+const PPQ: u64 = 480;
+
+fn convert(meta: &[(u64, Event)], config: &Config) -> Result<(Root, ConversionReport), TaikoError> {
+    let notes = [(0, Event::Note(60)), (PPQ * 3, Event::Note(61))];
+    let bytes = midi_file(metrical(PPQ as u16), meta, "Hard", &notes);
+    MidiConverter::from_bytes(bytes)
+        .with_config(config)
+        .convert_merged()
+}
+
+#[test]
+fn missing_tempo_fails_without_a_fallback() {
+    let result = convert(&[(0, Event::TimeSignature(4, 4))], &Config::default());
+
+    assert!(matches!(result, Err(TaikoError::NoTempo)));
+}
+
+#[test]
+fn rejects_fallback_tempos_the_gui_does_not_allow() {
+    let config = Config {
+        fallback_bpm: Some(0_f64),
+        ..Default::default()
+    };
+    let result = convert(&[(0, Event::TimeSignature(4, 4))], &config);
+
+    assert!(matches!(result, Err(TaikoError::InvalidSetting(_))));
+}
+
+#[test]
+fn missing_tempo_and_meter_use_the_midi_defaults() {
+    let config = Config {
+        fallback_bpm: Some(120.0),
+        ..Default::default()
+    };
+    let (root, report) = convert(&[], &config).unwrap();
+    let change = &root.custom_data.bpmchanges[0];
+
+    assert_eq!(root.custom_data.bpmchanges.len(), 1);
+    assert_eq!(
+        (change.time, change.bpm, change.beats_per_bar),
+        (0.0, 120.0, 4)
+    );
+    assert_eq!(root.notes[1].time, 3.0);
+    assert_eq!(report.fallback_bpm, Some(120.0));
+    assert_eq!(
+        report.fallback_time_signature,
+        Some(TimeSignature::default())
+    );
+}
+
+#[test]
+fn fallback_time_signature_sets_the_beat_unit() {
+    let config = Config {
+        fallback_time_signature: Some("6/8".parse().unwrap()),
+        ..Default::default()
+    };
+    let (root, report) = convert(&[(0, Event::Tempo(500_000))], &config).unwrap();
+    let change = &root.custom_data.bpmchanges[0];
+
+    assert_eq!((change.bpm, change.beats_per_bar), (240.0, 6));
+    assert_eq!(root.notes[1].time, 6.0);
+    assert_eq!(report.fallback_bpm, None);
+    assert_eq!(report.lines().len(), 1);
+}
+
+#[test]
+fn fallbacks_do_not_override_the_file() {
+    let config = Config {
+        fallback_bpm: Some(90.0),
+        fallback_time_signature: Some("7/8".parse().unwrap()),
+        ..Default::default()
+    };
+    let (root, report) = convert(
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(3, 4))],
+        &config,
+    )
+    .unwrap();
+    let change = &root.custom_data.bpmchanges[0];
+
+    assert_eq!((change.bpm, change.beats_per_bar), (120.0, 3));
+    assert!(report.is_empty());
+}
+
+#[test]
+fn parses_time_signatures() {
+    let parsed: TimeSignature = "12/8".parse().unwrap();
+
+    assert_eq!((parsed.numerator, parsed.denominator), (12, 8));
+    assert!("4".parse::<TimeSignature>().is_err());
+    assert!("0/4".parse::<TimeSignature>().is_err());
+    assert!("3/6".parse::<TimeSignature>().is_err());
+}
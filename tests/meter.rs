@@ -0,0 +1,178 @@
+mod common;
+
+use common::{metrical, midi_file, Event};
+use midly::{MetaMessage, Smf, TrackEventKind};
+use taiko::{
+    json_structures::custom::{Config, UnmappedNotePolicy},
+    json_structures::edda_objects::Root,
+    DatConverter, MidiConverter,
+};
+
+const PPQ: u64 = 480;
+
+fn convert(meta: &[(u64, Event)], notes: &[(u64, Event)]) -> Root {
+    let bytes = midi_file(metrical(PPQ as u16), meta, "Hard", notes);
+    let (root, _) = MidiConverter::from_bytes(bytes)
+        .with_config(&Config::default())
+        .convert_merged()
+        .unwrap();
+    root
+}
+
+/// Converts two bars of `numerator`/`denominator` at 120 quarter-note bpm with a note
+/// at the start of each bar.
+fn two_bars(numerator: u8, denominator: u8) -> Root {
+    let bar_ticks = numerator as u64 * PPQ * 4 / denominator as u64;
+    convert(
+        &[
+            (0, Event::Tempo(500_000)),
+            (0, Event::TimeSignature(numerator, denominator)),
+        ],
+        &[(0, Event::Note(60)), (bar_ticks, Event::Note(60))],
+    )
+}
+
+#[test]
+fn simple_meters_count_quarter_notes() {
+    let root = two_bars(3, 4);
+    let change = &root.custom_data.bpmchanges[0];
+
+    assert_eq!(root.custom_data.bpmchanges.len(), 1);
+    assert_eq!(change.bpm, 120.0);
+    assert_eq!(change.beats_per_bar, 3);
+    assert_eq!(change.metronome_offset, 3);
+    assert_eq!(root.notes[1].time, 3.0);
+}
+
+#[test]
+fn eighth_note_meters_count_eighth_notes() {
+    for (numerator, metronome_offset) in [(6, 3), (7, 7), (12, 3)] {
+        let root = two_bars(numerator, 8);
+        let change = &root.custom_data.bpmchanges[0];
+
+        assert_eq!(change.bpm, 240.0, "{}/8", numerator);
+        assert_eq!(change.beats_per_bar, numerator as i64, "{}/8", numerator);
+        assert_eq!(change.metronome_offset, metronome_offset, "{}/8", numerator);
+        assert_eq!(root.notes[1].time, numerator as f64, "{}/8", numerator);
+    }
+}
+
+#[test]
+fn meter_changes_keep_bar_starts_on_the_grid() {
+    // One bar of 4/4, two bars of 6/8, then one bar of 3/4.
+    let root = convert(
+        &[
+            (0, Event::Tempo(500_000)),
+            (0, Event::TimeSignature(4, 4)),
+            (4 * PPQ, Event::TimeSignature(6, 8)),
+            (10 * PPQ, Event::TimeSignature(3, 4)),
+        ],
+        &[
+            (0, Event::Note(60)),
+            (4 * PPQ, Event::Note(60)),
+            (7 * PPQ, Event::Note(60)),
+            (10 * PPQ, Event::Note(60)),
+            (13 * PPQ, Event::Note(60)),
+        ],
+    );
+    let changes = &root.custom_data.bpmchanges;
+    let times: Vec<f64> = root.notes.iter().map(|note| note.time).collect();
+
+    assert_eq!(times, vec![0.0, 4.0, 10.0, 16.0, 19.0]);
+    assert_eq!(changes.len(), 3);
+    assert_eq!(
+        changes
+            .iter()
+            .map(|c| (c.time, c.bpm, c.beats_per_bar))
+            .collect::<Vec<_>>(),
+        vec![(0.0, 120.0, 4), (4.0, 240.0, 6), (16.0, 120.0, 3)]
+    );
+}
+
+#[test]
+fn tempo_changes_inside_compound_meters_are_scaled() {
+    let root = convert(
+        &[
+            (0, Event::Tempo(500_000)),
+            (0, Event::TimeSignature(6, 8)),
+            (3 * PPQ, Event::Tempo(1_000_000)),
+        ],
+        &[(0, Event::Note(60))],
+    );
+    let changes = &root.custom_data.bpmchanges;
+
+    assert_eq!(changes.len(), 2);
+    assert_eq!((changes[1].time, changes[1].bpm), (6.0, 120.0));
+    assert_eq!(changes[1].beats_per_bar, 6);
+    assert_eq!(changes[1].metronome_offset, 3);
+}
+
+#[test]
+fn tempo_changes_inside_a_bar_do_not_start_a_new_one() {
+    let config = Config {
+        unmapped_notes: UnmappedNotePolicy::Skip,
+        ..Default::default()
+    };
+    // A tempo change on beat 3 of the first bar of 4/4, and a 3/4 bar from bar 3 on.
+    let bytes = midi_file(
+        metrical(PPQ as u16),
+        &[
+            (0, Event::Tempo(500_000)),
+            (0, Event::TimeSignature(4, 4)),
+            (2 * PPQ, Event::Tempo(400_000)),
+            (8 * PPQ, Event::TimeSignature(3, 4)),
+        ],
+        "Hard",
+        &[(5 * PPQ, Event::Note(30)), (12 * PPQ, Event::Note(30))],
+    );
+    let (_, report) = MidiConverter::from_bytes(bytes)
+        .with_config(&config)
+        .convert_merged()
+        .unwrap();
+    let positions: Vec<(u64, f64)> = report.unmapped.iter().map(|n| (n.bar, n.beat)).collect();
+
+    assert_eq!(positions, vec![(2, 2.0), (4, 2.0)]);
+}
+
+#[test]
+fn compound_meters_survive_converting_back_to_midi() {
+    // One bar of 4/4, two bars of 6/8 at a new tempo, then one bar of 3/4.
+    let meta = [
+        (0, Event::Tempo(500_000)),
+        (0, Event::TimeSignature(4, 4)),
+        (4 * PPQ, Event::TimeSignature(6, 8)),
+        (4 * PPQ, Event::Tempo(600_000)),
+        (10 * PPQ, Event::TimeSignature(3, 4)),
+    ];
+    let notes = [
+        (0, Event::Note(60)),
+        (5 * PPQ, Event::Note(61)),
+        (10 * PPQ, Event::Note(62)),
+        (12 * PPQ, Event::Note(63)),
+    ];
+    let root = convert(&meta, &notes);
+
+    let midi = DatConverter::from_bytes(serde_json::to_vec(&root).unwrap())
+        .with_config(&Config::default())
+        .with_track_name("Hard")
+        .convert()
+        .unwrap()
+        .midi;
+    let meters: Vec<(u8, u8)> = Smf::parse(&midi).unwrap().tracks[0]
+        .iter()
+        .filter_map(|event| match event.kind {
+            TrackEventKind::Meta(MetaMessage::TimeSignature(numerator, denominator, ..)) => {
+                Some((numerator, denominator))
+            }
+            _ => None,
+        })
+        .collect();
+    let (again, _) = MidiConverter::from_bytes(midi)
+        .with_config(&Config::default())
+        .convert_merged()
+        .unwrap();
+
+    assert_eq!(meters, vec![(4, 2), (6, 3), (3, 2)]);
+    assert_eq!(again.custom_data.bpmchanges, root.custom_data.bpmchanges);
+    assert_eq!(again.notes, root.notes);
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::{metrical, midi_file, Event};
+use taiko::{
+    json_structures::custom::{Config, OsuHit, SongMetadata},
+    MidiConverter, OsuBeatmap,
+};
+
+const PPQ: u64 = 480;
+
+fn export(meta: &[(u64, Event)], notes: &[(u64, Event)], config: &Config) -> String {
+    let bytes = midi_file(metrical(PPQ as u16), meta, "Hard", notes);
+    let (root, _) = MidiConverter::from_bytes(bytes)
+        .with_config(config)
+        .convert_merged()
+        .unwrap();
+    let song = SongMetadata {
+        song_name: "Song".to_string(),
+        song_author_name: "Band".to_string(),
+        ..Default::default()
+    };
+    let osu = OsuBeatmap::new(&song, "Hard", &root).with_config(config);
+    assert_eq!(osu.file_name(), "Band - Song (taiko) [Hard].osu");
+    osu.to_osu_string()
+}
+
+fn section<'a>(osu: &'a str, name: &str) -> Vec<&'a str> {
+    osu.split(&format!("[{}]\n", name))
+        .nth(1)
+        .unwrap()
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .collect()
+}
+
+#[test]
+fn writes_timing_points_in_milliseconds() {
+    let osu = export(
+        &[
+            (0, Event::Tempo(500_000)),
+            (0, Event::TimeSignature(4, 4)),
+            (PPQ * 4, Event::Tempo(400_000)),
+        ],
+        &[(0, Event::Note(60)), (PPQ * 5, Event::Note(60))],
+        &Config::default(),
+    );
+
+    assert!(osu.contains("Mode: 1"));
+    assert_eq!(
+        section(&osu, "TimingPoints"),
+        vec!["0,500,4,1,0,100,1,0", "2000,400,4,1,0,100,1,0"]
+    );
+    assert_eq!(
+        section(&osu, "HitObjects"),
+        vec!["256,192,0,1,8,0:0:0:0:", "256,192,2400,1,8,0:0:0:0:"]
+    );
+}
+
+#[test]
+fn lane_rules_pick_don_kat_and_big_notes() {
+    let config = Config {
+        osu_hits: vec![OsuHit::Don, OsuHit::Kat, OsuHit::BigDon, OsuHit::BigKat],
+        ..Default::default()
+    };
+    let notes: Vec<(u64, Event)> = (0..4)
+        .map(|lane| (lane * PPQ, Event::Note(60 + lane as u8)))
+        .collect();
+    let osu = export(
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        &notes,
+        &config,
+    );
+    let hitsounds: Vec<&str> = section(&osu, "HitObjects")
+        .iter()
+        .map(|line| line.split(',').nth(4).unwrap())
+        .collect();
+
+    assert_eq!(hitsounds, vec!["0", "8", "4", "12"]);
+}
+
+#[test]
+fn simultaneous_notes_of_one_colour_become_a_big_hit() {
+    let osu = export(
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        &[
+            (0, Event::Note(61)),
+            (0, Event::Note(62)),
+            (PPQ, Event::Note(60)),
+            (PPQ, Event::Note(61)),
+        ],
+        &Config::default(),
+    );
+
+    assert_eq!(
+        section(&osu, "HitObjects"),
+        vec!["256,192,0,1,4,0:0:0:0:", "256,192,500,1,8,0:0:0:0:"]
+    );
+}
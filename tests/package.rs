@@ -0,0 +1,60 @@
+mod common;
+
+use std::{fs, path::Path};
+
+use common::folder;
+use taiko::{
+    json_structures::{custom::SongMetadata, edda_objects::Root},
+    package::SongPackage,
+    TaikoError,
+};
+
+fn package(folder: &Path, audio: &str) -> SongPackage {
+    SongPackage::new(
+        &SongMetadata::default(),
+        vec![("Hard".to_string(), Root::default())],
+        ".dat",
+        folder.join(audio),
+        folder.join("cover.jpg"),
+    )
+}
+
+#[test]
+fn validates_the_audio_and_cover_files() {
+    let folder = folder("package-validate");
+    fs::write(folder.join("song.ogg"), "ogg").unwrap();
+    fs::write(folder.join("song.mp3"), "mp3").unwrap();
+
+    let no_cover = package(&folder, "song.ogg").validate();
+    fs::write(folder.join("cover.jpg"), "jpg").unwrap();
+    let valid = package(&folder, "song.ogg").validate();
+    let mp3 = package(&folder, "song.mp3").validate();
+    let no_audio = package(&folder, "missing.ogg").validate();
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert!(matches!(no_cover, Err(TaikoError::Io { path, .. }) if path.ends_with("cover.jpg")));
+    assert!(valid.is_ok());
+    assert!(matches!(mp3, Err(TaikoError::InvalidPackage(_))));
+    assert!(matches!(no_audio, Err(TaikoError::Io { path, .. }) if path.ends_with("missing.ogg")));
+}
+
+#[test]
+fn rejects_info_dat_names_that_do_not_match_the_files() {
+    let folder = folder("package-names");
+    fs::write(folder.join("song.ogg"), "ogg").unwrap();
+    fs::write(folder.join("cover.jpg"), "jpg").unwrap();
+
+    let mut audio = package(&folder, "song.ogg");
+    audio.info.song_filename = "other.ogg".to_string();
+    let mut cover = package(&folder, "song.ogg");
+    cover.info.cover_image_filename = "cover.png".to_string();
+    let mut beatmap = package(&folder, "song.ogg");
+    beatmap.info.difficulty_beatmap_sets[0].difficulty_beatmaps[0].beatmap_filename =
+        "Expert.dat".to_string();
+    let results = [audio.validate(), cover.validate(), beatmap.validate()];
+    fs::remove_dir_all(&folder).unwrap();
+
+    for result in results {
+        assert!(matches!(result, Err(TaikoError::InvalidPackage(_))));
+    }
+}
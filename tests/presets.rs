@@ -0,0 +1,35 @@
+use taiko::json_structures::custom::{Config, DrumMapPreset};
+
+#[test]
+fn gm_preset_puts_kit_pieces_in_lanes() {
+    let mut config = Config::default();
+    config.drum_map = config.preset("GM 4-lane").unwrap().drum_map;
+
+    let lanes: Vec<Option<usize>> = [36, 38, 45, 42, 49, 51, 60]
+        .iter()
+        .map(|&pitch| config.lane_for_pitch(pitch))
+        .collect();
+    assert_eq!(
+        lanes,
+        vec![Some(0), Some(1), Some(2), Some(2), Some(3), Some(3), None]
+    );
+}
+
+#[test]
+fn built_in_presets_have_four_lanes() {
+    for preset in DrumMapPreset::built_in() {
+        assert_eq!(preset.drum_map.len(), 4, "{}", preset.name);
+    }
+}
+
+#[test]
+fn saved_presets_are_found_by_name_and_replace_older_ones() {
+    let mut config = Config::default();
+    config.save_preset("Mine");
+    config.drum_map = config.preset("rock band pads").unwrap().drum_map;
+    config.save_preset("mine ");
+
+    assert_eq!(config.drum_map_presets.len(), 1);
+    assert_eq!(config.preset("MINE").unwrap().drum_map, config.drum_map);
+    assert!(config.preset("missing").is_none());
+}
@@ -0,0 +1,79 @@
+use taiko::{
+    json_structures::custom::{Config, ProfileStore},
+    TaikoError,
+};
+
+#[test]
+fn reads_configs_from_before_profiles() {
+    let json = r#"{"drum_map": [36, 38, 42, 49], "batch_output_extension": ".json"}"#;
+    let profiles: ProfileStore = serde_json::from_str(json).unwrap();
+
+    assert_eq!(profiles.active_profile, "Default");
+    assert_eq!(profiles.profiles.len(), 1);
+    assert_eq!(profiles.active().batch_output_extension, ".json");
+    assert_eq!(profiles.active().lane_for_pitch(42), Some(2));
+}
+
+#[test]
+fn round_trips_profiles() {
+    let mut profiles = ProfileStore::default();
+    let rock_band = Config {
+        batch_output_extension: ".rb.dat".to_owned(),
+        ..Default::default()
+    };
+    profiles.create("Rock Band", rock_band.clone()).unwrap();
+    profiles.select("rock band").unwrap();
+
+    let saved = serde_json::to_string(&profiles).unwrap();
+    let loaded: ProfileStore = serde_json::from_str(&saved).unwrap();
+
+    assert!(saved.contains(r#""active_profile":"Rock Band""#));
+    assert!(saved.contains(r#""name":"Rock Band","drum_map""#));
+    assert_eq!(loaded, profiles);
+    assert_eq!(loaded.active(), &rock_band);
+}
+
+#[test]
+fn creates_copies_and_deletes_profiles() {
+    let mut profiles = ProfileStore::default();
+    let edited = Config {
+        time_precision: 3,
+        ..Default::default()
+    };
+    profiles.set_config("default", edited.clone()).unwrap();
+    profiles.copy("Default", "Practice").unwrap();
+    profiles.select("Practice").unwrap();
+
+    assert_eq!(profiles.active(), &edited);
+    assert!(matches!(
+        profiles.create("practice", Config::default()),
+        Err(TaikoError::ProfileConflict(_))
+    ));
+    assert!(matches!(
+        profiles.create("  ", Config::default()),
+        Err(TaikoError::ProfileConflict(_))
+    ));
+    assert!(matches!(
+        profiles.copy("Missing", "Other"),
+        Err(TaikoError::UnknownProfile(_))
+    ));
+
+    // Deleting the profile in use falls back to the first one.
+    profiles.delete("PRACTICE").unwrap();
+    assert_eq!(profiles.active_profile, "Default");
+    assert!(matches!(
+        profiles.delete("Default"),
+        Err(TaikoError::ProfileConflict(_))
+    ));
+}
+
+#[test]
+fn falls_back_to_the_first_profile() {
+    let json = r#"{"active_profile": "Gone", "profiles": [
+        {"name": "Studio", "drum_map": [36, 38, 42, 49], "batch_output_extension": ".dat"}
+    ]}"#;
+    let profiles: ProfileStore = serde_json::from_str(json).unwrap();
+
+    assert_eq!(profiles.active_profile, "Studio");
+    assert_eq!(profiles.active().lane_for_pitch(49), Some(3));
+}
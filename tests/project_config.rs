@@ -0,0 +1,72 @@
+mod common;
+
+use std::fs;
+
+use common::folder;
+use serde_json::json;
+use taiko::{
+    file_io::{self, PROJECT_FILE},
+    json_structures::custom::{Config, UnmappedNotePolicy},
+    TaikoError,
+};
+
+#[test]
+fn finds_the_nearest_project_file() {
+    let root = folder("project-find");
+    let album = root.join("album");
+    let song = album.join("song");
+    fs::create_dir_all(&song).unwrap();
+    fs::write(root.join(PROJECT_FILE), "{}").unwrap();
+
+    let from_above = file_io::find_project_file(song.join("song.mid"));
+    fs::write(album.join(PROJECT_FILE), "{}").unwrap();
+    let nearest = file_io::find_project_file(song.join("song.mid"));
+    let from_folder = file_io::find_project_file(&album);
+    let root = root.canonicalize().unwrap();
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(from_above, Some(root.join(PROJECT_FILE)));
+    assert_eq!(nearest, Some(root.join("album").join(PROJECT_FILE)));
+    assert_eq!(from_folder, nearest);
+}
+
+#[test]
+fn overrides_only_the_given_settings() {
+    let config = Config {
+        time_precision: 3,
+        ..Default::default()
+    };
+    let overrides = json!({"drum_map": [36, 38, "42-46", 49], "unmapped_notes": "Skip"});
+
+    let merged = config.with_overrides(&overrides).unwrap();
+
+    assert_eq!(merged.time_precision, 3);
+    assert_eq!(merged.unmapped_notes, UnmappedNotePolicy::Skip);
+    assert_eq!(merged.lane_for_pitch(44), Some(2));
+    assert_eq!(merged.batch_output_extension, config.batch_output_extension);
+}
+
+#[test]
+fn rejects_unknown_and_invalid_settings() {
+    let config = Config::default();
+
+    assert!(config.with_overrides(&json!({"drum_mapp": []})).is_err());
+    assert!(config
+        .with_overrides(&json!({"time_precision": "six"}))
+        .is_err());
+    assert!(config.with_overrides(&json!([1, 2])).is_err());
+    assert!(config
+        .with_overrides(&json!({"fallback_bpm": -120}))
+        .is_err());
+    assert!(config
+        .with_overrides(&json!({"track_overrides": {"Hard": {"drum_map": [36, [], 42]}}}))
+        .is_err());
+
+    let folder = folder("project-invalid");
+    let path = folder.join(PROJECT_FILE);
+    fs::write(&path, r#"{"fallback_bpm": 120,}"#).unwrap();
+    let result = file_io::apply_project_file(&config, &path);
+    fs::remove_dir_all(&folder).unwrap();
+
+    assert!(matches!(result, Err(TaikoError::InvalidProjectFile { .. })));
+}
@@ -0,0 +1,215 @@
+mod common;
+
+use common::{metrical, midi_file, Event};
+use taiko::{
+    json_structures::custom::Config, reaper::RppProject, Conversion, MidiConverter, TaikoError,
+};
+
+fn convert(project: &str) -> Result<Conversion, TaikoError> {
+    let midi = RppProject::from_text(project).to_midi()?;
+    MidiConverter::from_bytes(midi).convert()
+}
+
+fn times(conversion: &Conversion, difficulty: &str) -> Vec<(f64, i64)> {
+    let (_, root) = conversion
+        .difficulties
+        .iter()
+        .find(|(name, _)| name == difficulty)
+        .unwrap();
+    root.notes.iter().map(|n| (n.time, n.line_index)).collect()
+}
+
+const PROJECT: &str = r#"<REAPER_PROJECT 0.1 "6.80/linux-x86_64" 1681234567
+  TEMPO 120 4 4
+  <TRACK {8F5C2A4E-0000-0000-0000-000000000001}
+    NAME "Hard"
+    <ITEM
+      POSITION 1
+      LENGTH 4
+      <SOURCE MIDI
+        HASDATA 1 960 QN
+        E 0 90 3c 64
+        E 480 80 3c 00
+        E 480 90 3d 64
+        Em 960 90 3e 64
+        <X 0 0
+          /wNIYXJk
+        >
+        E 0 90 3f 64
+        E 7680 90 3c 64
+      >
+    >
+    <ITEM
+      POSITION 0
+      LENGTH 4
+      MUTE 1 0
+      <SOURCE MIDI
+        HASDATA 1 960 QN
+        E 0 90 3c 64
+      >
+    >
+  >
+  <TRACK
+    NAME "Audio"
+    <ITEM
+      POSITION 0
+      LENGTH 10
+      <SOURCE WAVE
+        FILE "song.ogg"
+      >
+    >
+  >
+  <TRACK
+    NAME Expert
+    <ITEM
+      POSITION 0
+      LENGTH 2
+      <SOURCE MIDI
+        HASDATA 1 480 QN
+        E 240 90 3e 64
+      >
+    >
+  >
+>
+"#;
+
+#[test]
+fn reads_in_project_midi_items_on_named_tracks() {
+    let conversion = convert(PROJECT).unwrap();
+    let names: Vec<&str> = conversion
+        .difficulties
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    assert_eq!(names, vec!["Hard", "Expert"]);
+    // The item starts two beats in, and its last note falls past the item's end.
+    assert_eq!(
+        times(&conversion, "Hard"),
+        vec![(2.0, 0), (3.0, 1), (4.0, 3)]
+    );
+    assert_eq!(times(&conversion, "Expert"), vec![(0.5, 2)]);
+}
+
+#[test]
+fn follows_the_tempo_envelope() {
+    // 60 bpm in 3/4 from two seconds (beat four) on, where the item starts a second later.
+    let project = r#"<REAPER_PROJECT 0.1 "6.80" 0
+  TEMPO 120 4 4
+  <TEMPOENVEX
+    EGUID {00000000-0000-0000-0000-000000000000}
+    ACT 1 -1
+    PT 0 120 1
+    PT 2 60 1 262147
+  >
+  <TRACK
+    NAME Hard
+    <ITEM
+      POSITION 3
+      LENGTH 2
+      <SOURCE MIDI
+        HASDATA 1 960 QN
+        E 0 90 3c 64
+        E 960 90 3d 64
+      >
+    >
+  >
+>
+"#;
+    let conversion = convert(project).unwrap();
+    let (_, root) = &conversion.difficulties[0];
+    let changes: Vec<(f64, f64, i64)> = root
+        .custom_data
+        .bpmchanges
+        .iter()
+        .map(|change| (change.time, change.bpm, change.beats_per_bar))
+        .collect();
+
+    assert_eq!(changes, vec![(0.0, 120.0, 4), (4.0, 60.0, 3)]);
+    assert_eq!(times(&conversion, "Hard"), vec![(5.0, 0), (6.0, 1)]);
+}
+
+#[test]
+fn reads_referenced_midi_files_next_to_the_project() {
+    let folder = std::env::temp_dir().join(format!("taiko-reaper-{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    let notes: Vec<(u64, Event)> = [0, 1, 2, 5]
+        .iter()
+        .map(|&beat| (beat * 480, Event::Note(60 + beat as u8 % 4)))
+        .collect();
+    let midi = midi_file(metrical(480), &[], "drums", &notes);
+    std::fs::write(folder.join("drums.mid"), midi).unwrap();
+    // The item starts half a second (one beat) into the file and is two seconds long.
+    let project = r#"<REAPER_PROJECT 0.1 "6.80" 0
+  TEMPO 120 4 4
+  <TRACK
+    NAME Normal
+    <ITEM
+      POSITION 0
+      LENGTH 2
+      SOFFS 0.5
+      <SOURCE MIDI
+        FILE "drums.mid"
+      >
+    >
+  >
+>
+"#;
+    let path = folder.join("song.rpp");
+    std::fs::write(&path, project).unwrap();
+
+    let conversion = MidiConverter::from_file(&path).unwrap().convert();
+    std::fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(
+        times(&conversion.unwrap(), "Normal"),
+        vec![(0.0, 1), (1.0, 2)]
+    );
+}
+
+#[test]
+fn rejects_broken_projects() {
+    let unterminated = "<REAPER_PROJECT 0.1\n  <TRACK\n    NAME Hard\n  >\n";
+    let not_a_project = "<RECORD_CFG\n>\n";
+
+    assert!(matches!(
+        convert(unterminated),
+        Err(TaikoError::RppParse(_))
+    ));
+    assert!(matches!(
+        convert(not_a_project),
+        Err(TaikoError::RppParse(_))
+    ));
+}
+
+#[test]
+fn keeps_note_velocities_for_track_thresholds() {
+    let project = r#"<REAPER_PROJECT 0.1 "6.80/linux-x86_64" 1681234567
+  TEMPO 120 4 4
+  <TRACK
+    NAME "Hard"
+    <ITEM
+      POSITION 0
+      LENGTH 4
+      <SOURCE MIDI
+        HASDATA 1 960 QN
+        E 0 90 3c 64
+        E 960 90 3d 14
+        E 960 90 3e 50
+      >
+    >
+  >
+>
+"#;
+    let config: Config =
+        serde_json::from_str(r#"{"track_overrides": {"Hard": {"min_velocity": 40}}}"#).unwrap();
+    let midi = RppProject::from_text(project).to_midi().unwrap();
+    let conversion = MidiConverter::from_bytes(midi)
+        .with_config(&config)
+        .convert()
+        .unwrap();
+
+    // The ghost note at velocity 20 (0x14) is left out.
+    assert_eq!(times(&conversion, "Hard"), vec![(0.0, 0), (2.0, 2)]);
+    assert_eq!(conversion.report.soft_notes, vec![("Hard".to_string(), 1)]);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{metrical, midi_file_with_tracks, Event};
+use taiko::{
+    json_structures::custom::{Config, InputFormat},
+    Conversion, MidiConverter, TaikoError,
+};
+
+const PPQ: u64 = 480;
+
+fn convert(tracks: &[(&str, &[(u64, Event)])], config: &Config) -> Result<Conversion, TaikoError> {
+    let bytes = midi_file_with_tracks(
+        metrical(PPQ as u16),
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        tracks,
+    );
+    MidiConverter::from_bytes(bytes)
+        .with_config(config)
+        .convert()
+}
+
+fn rock_band_config() -> Config {
+    Config {
+        input_format: InputFormat::RockBand,
+        ..Default::default()
+    }
+}
+
+/// Kick, red, yellow and blue of the tier starting at `kick`, one per beat from `beat`.
+fn tier(kick: u8, beat: u64) -> Vec<(u64, Event)> {
+    (0..4)
+        .map(|pad| ((beat + pad as u64) * PPQ, Event::Note(kick + pad)))
+        .collect()
+}
+
+#[test]
+fn splits_the_drum_track_into_difficulties() {
+    let mut drums = Vec::new();
+    drums.extend(tier(60, 0));
+    drums.extend(tier(72, 4));
+    drums.extend(tier(84, 8));
+    drums.extend(tier(96, 12));
+    // Overdrive and solo markers are not notes of any tier.
+    drums.push((0, Event::Note(116)));
+    drums.push((0, Event::Note(103)));
+    let guitar = tier(96, 0);
+
+    let conversion = convert(
+        &[("PART GUITAR", &guitar), ("PART DRUMS", &drums)],
+        &rock_band_config(),
+    )
+    .unwrap();
+    let names: Vec<&str> = conversion
+        .difficulties
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    assert_eq!(names, vec!["Easy", "Medium", "Hard", "Expert"]);
+    for (i, (_, root)) in conversion.difficulties.iter().enumerate() {
+        let lanes: Vec<i64> = root.notes.iter().map(|n| n.line_index).collect();
+        let times: Vec<f64> = root.notes.iter().map(|n| n.time).collect();
+        let first_beat = i as f64 * 4.0;
+        assert_eq!(lanes, vec![0, 1, 2, 3]);
+        assert_eq!(
+            times,
+            (0..4).map(|b| first_beat + b as f64).collect::<Vec<_>>()
+        );
+    }
+    assert!(conversion.report.unmapped.is_empty());
+}
+
+#[test]
+fn leaves_out_tiers_without_notes() {
+    let drums = tier(96, 0);
+    let conversion = convert(&[("part drums", &drums)], &rock_band_config()).unwrap();
+
+    assert_eq!(conversion.difficulties.len(), 1);
+    assert_eq!(conversion.difficulties[0].0, "Expert");
+}
+
+#[test]
+fn fails_without_a_drum_track() {
+    let guitar = tier(96, 0);
+    let result = convert(&[("PART GUITAR", &guitar)], &rock_band_config());
+
+    assert!(matches!(result, Err(TaikoError::TrackNotFound(_))));
+}
@@ -0,0 +1,244 @@
+mod common;
+
+use common::{metrical, midi_file, Event};
+use taiko::{
+    json_structures::{
+        custom::{Config, SongMetadata},
+        edda_objects::Root,
+    },
+    stepmania::{SmExporter, SmImporter},
+    MidiConverter, TaikoError,
+};
+
+const SM: &str = "#TITLE:Song;
+#ARTIST:Band;
+#MUSIC:song.ogg;
+#OFFSET:0.000;
+#BPMS:0.000=120.000,
+8.000=240.000;
+#STOPS:4.000=0.500;
+
+//--------------- dance-single - Hard ----------------
+#NOTES:
+     dance-single:
+     :
+     Medium:
+     5:
+     0,0,0,0,0:
+1000
+0100
+0010
+0001
+,
+1000
+0000
+0200
+0000
+0030
+0000
+M000
+0000
+,
+1001
+0000
+0000
+0000
+;
+#NOTES:
+     dance-double:
+     :
+     Hard:
+     5:
+     0,0,0,0,0:
+10000000
+;
+";
+
+fn assert_seconds(root: &Root, expected: &[f64]) {
+    let seconds: Vec<f64> = root.notes.iter().map(|n| root.seconds_at(n.time)).collect();
+    assert_eq!(seconds.len(), expected.len());
+    for (actual, expected) in seconds.iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-9, "{:?}", seconds);
+    }
+}
+
+fn beats_and_lanes(root: &Root) -> Vec<(f64, i64)> {
+    root.notes.iter().map(|n| (n.time, n.line_index)).collect()
+}
+
+#[test]
+fn imports_dance_single_charts() {
+    let imported = SmImporter::from_text(SM).import().unwrap();
+    let (name, root) = &imported.difficulties[0];
+
+    assert_eq!(imported.difficulties.len(), 1);
+    assert_eq!(name, "Normal");
+    assert_eq!(imported.song.song_name, "Song");
+    assert_eq!(imported.song.song_author_name, "Band");
+    assert_eq!(
+        beats_and_lanes(root),
+        vec![
+            (0.0, 0),
+            (1.0, 1),
+            (2.0, 2),
+            (3.0, 3),
+            (4.0, 0),
+            (5.0, 1),
+            (8.0, 0),
+            (8.0, 3)
+        ]
+    );
+}
+
+#[test]
+fn stops_keep_notes_at_their_time_in_seconds() {
+    let imported = SmImporter::from_text(SM).import().unwrap();
+
+    // Beats take half a second until the bpm doubles at beat 8, and the stop adds half a
+    // second after the note on beat 4.
+    assert_seconds(
+        &imported.difficulties[0].1,
+        &[0.0, 0.5, 1.0, 1.5, 2.0, 3.0, 4.5, 4.5],
+    );
+}
+
+#[test]
+fn offsets_keep_notes_at_their_time_in_seconds() {
+    let late = SM.replace("#OFFSET:0.000;", "#OFFSET:-1.000;");
+    let early = SM.replace("#OFFSET:0.000;", "#OFFSET:0.100;");
+
+    let late = SmImporter::from_text(late).import().unwrap();
+    let early = SmImporter::from_text(early).import().unwrap();
+
+    assert_seconds(
+        &late.difficulties[0].1,
+        &[1.0, 1.5, 2.0, 2.5, 3.0, 4.0, 5.5, 5.5],
+    );
+    assert_seconds(
+        &early.difficulties[0].1,
+        &[0.0, 0.4, 0.9, 1.4, 1.9, 2.9, 4.4, 4.4],
+    );
+}
+
+#[test]
+fn imports_ssc_note_data() {
+    let ssc = "#VERSION:0.83;
+#TITLE:Song;
+#BPMS:0=150;
+#NOTEDATA:;
+#STEPSTYPE:dance-single;
+#DIFFICULTY:Challenge;
+#BPMS:0=100;
+#NOTES:
+0000
+0100
+0000
+0000
+;
+#NOTEDATA:;
+#STEPSTYPE:dance-single;
+#DIFFICULTY:Easy;
+#NOTES:
+1000
+;
+";
+    let imported = SmImporter::from_text(ssc).import().unwrap();
+    let names: Vec<&str> = imported
+        .difficulties
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    assert_eq!(names, vec!["Expert", "Easy"]);
+    assert_eq!(
+        imported.difficulties[0].1.custom_data.bpmchanges[0].bpm,
+        100.0
+    );
+    assert_eq!(
+        imported.difficulties[1].1.custom_data.bpmchanges[0].bpm,
+        150.0
+    );
+    assert_eq!(beats_and_lanes(&imported.difficulties[0].1), vec![(1.0, 1)]);
+}
+
+#[test]
+fn rejects_malformed_timing() {
+    let broken = SM.replace("8.000=240.000", "8.000=fast");
+    let result = SmImporter::from_text(broken).import();
+
+    assert!(matches!(result, Err(TaikoError::StepmaniaParse(_))));
+}
+
+#[test]
+fn exported_charts_import_back() {
+    let notes: Vec<(u64, Event)> = [
+        (0, 60),
+        (160, 61),
+        (320, 62),
+        (480, 63),
+        (2040, 60),
+        (2040, 63),
+    ]
+    .into_iter()
+    .map(|(ticks, key)| (ticks, Event::Note(key)))
+    .collect();
+    let bytes = midi_file(
+        metrical(480),
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        "Hard",
+        &notes,
+    );
+    let difficulties = MidiConverter::from_bytes(bytes)
+        .convert()
+        .unwrap()
+        .difficulties;
+    let song = SongMetadata {
+        song_name: "Song".to_string(),
+        ..Default::default()
+    };
+
+    let exporter = SmExporter::new(&song, &difficulties);
+    let sm = exporter.to_sm_string();
+    let imported = SmImporter::from_text(sm.as_str()).import().unwrap();
+
+    assert_eq!(exporter.file_name(), "Song.sm");
+    assert!(sm.contains("#BPMS:0.000000=120.000000;"));
+    assert_eq!(imported.difficulties[0].0, "Hard");
+    assert_eq!(
+        beats_and_lanes(&imported.difficulties[0].1),
+        beats_and_lanes(&difficulties[0].1)
+    );
+}
+
+#[test]
+fn counts_notes_without_a_panel() {
+    let notes: Vec<(u64, Event)> = (0..6)
+        .map(|lane| (lane * 480, Event::Note(60 + lane as u8)))
+        .collect();
+    let bytes = midi_file(
+        metrical(480),
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        "Hard",
+        &notes,
+    );
+    let config = Config {
+        drum_map: (60..66)
+            .map(|pitch| pitch.to_string().parse().unwrap())
+            .collect(),
+        ..Default::default()
+    };
+    let difficulties = MidiConverter::from_bytes(bytes)
+        .with_config(&config)
+        .convert()
+        .unwrap()
+        .difficulties;
+    let song = SongMetadata::default();
+
+    let exporter = SmExporter::new(&song, &difficulties);
+    let imported = SmImporter::from_text(exporter.to_sm_string().as_str())
+        .import()
+        .unwrap();
+
+    assert_eq!(exporter.dropped_notes(), vec![("Hard".to_string(), 2)]);
+    assert_eq!(imported.difficulties[0].1.notes.len(), 4);
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use common::{midi_file, timecode, Event};
+use midly::Fps;
+use taiko::{
+    json_structures::{custom::Config, edda_objects::Root},
+    MidiConverter, TaikoError,
+};
+
+const SUBFRAMES: u8 = 40;
+
+fn convert(
+    fps: Fps,
+    meta: &[(u64, Event)],
+    notes: &[(u64, Event)],
+    config: &Config,
+) -> Result<Root, TaikoError> {
+    let bytes = midi_file(timecode(fps, SUBFRAMES), meta, "Hard", notes);
+    MidiConverter::from_bytes(bytes)
+        .with_config(config)
+        .convert_merged()
+        .map(|(root, _)| root)
+}
+
+/// Notes on every second for ten seconds at 120 bpm land on every second beat.
+fn assert_whole_seconds(fps: Fps, frames_per_second: u64) {
+    let ticks_per_second = frames_per_second * SUBFRAMES as u64;
+    let notes: Vec<(u64, Event)> = (0..10)
+        .map(|s| (s * ticks_per_second, Event::Note(60)))
+        .collect();
+    let root = convert(
+        fps,
+        &[(0, Event::Tempo(500_000))],
+        &notes,
+        &Config::default(),
+    )
+    .unwrap();
+
+    let times: Vec<f64> = root.notes.iter().map(|n| n.time).collect();
+    let expected: Vec<f64> = (0..10).map(|s| s as f64 * 2.0).collect();
+    assert_eq!(times, expected);
+    assert_eq!(root.custom_data.bpmchanges[0].bpm, 120.0);
+}
+
+#[test]
+fn converts_24_fps() {
+    assert_whole_seconds(Fps::Fps24, 24);
+}
+
+#[test]
+fn converts_25_fps() {
+    assert_whole_seconds(Fps::Fps25, 25);
+}
+
+#[test]
+fn converts_30_fps() {
+    assert_whole_seconds(Fps::Fps30, 30);
+}
+
+#[test]
+fn converts_29_97_drop_frame() {
+    // 30 frames at 29.97 fps last 1.001 seconds.
+    let frame = SUBFRAMES as u64;
+    let notes: Vec<(u64, Event)> = (0..10).map(|s| (s * 30 * frame, Event::Note(60))).collect();
+    let root = convert(
+        Fps::Fps29,
+        &[(0, Event::Tempo(500_000))],
+        &notes,
+        &Config::default(),
+    )
+    .unwrap();
+
+    let times: Vec<f64> = root.notes.iter().map(|n| n.time).collect();
+    let expected: Vec<f64> = (0..10).map(|s| s as f64 * 2.002).collect();
+    for (time, expected) in times.iter().zip(expected) {
+        assert!((time - expected).abs() < 1e-6, "{} != {}", time, expected);
+    }
+}
+
+#[test]
+fn follows_tempo_changes() {
+    // 120 bpm for four seconds, then 60 bpm.
+    let second = 25 * SUBFRAMES as u64;
+    let meta = [
+        (0, Event::Tempo(500_000)),
+        (4 * second, Event::Tempo(1_000_000)),
+    ];
+    let notes: Vec<(u64, Event)> = (0..8).map(|s| (s * second, Event::Note(61))).collect();
+    let root = convert(Fps::Fps25, &meta, &notes, &Config::default()).unwrap();
+
+    let times: Vec<f64> = root.notes.iter().map(|n| n.time).collect();
+    assert_eq!(times, vec![0.0, 2.0, 4.0, 6.0, 8.0, 9.0, 10.0, 11.0]);
+
+    let changes = &root.custom_data.bpmchanges;
+    assert_eq!(changes.len(), 2);
+    assert_eq!((changes[1].time, changes[1].bpm), (8.0, 60.0));
+}
+
+#[test]
+fn uses_fallback_bpm_without_tempo_map() {
+    let second = 30 * SUBFRAMES as u64;
+    let notes: Vec<(u64, Event)> = (0..4).map(|s| (s * second, Event::Note(62))).collect();
+
+    let missing = convert(Fps::Fps30, &[], &notes, &Config::default());
+    assert!(matches!(missing, Err(TaikoError::NoTempo)));
+
+    let config = Config {
+        fallback_bpm: Some(90.0),
+        ..Default::default()
+    };
+    let root = convert(Fps::Fps30, &[], &notes, &config).unwrap();
+    let times: Vec<f64> = root.notes.iter().map(|n| n.time).collect();
+    assert_eq!(times, vec![0.0, 1.5, 3.0, 4.5]);
+    assert_eq!(root.custom_data.bpmchanges[0].bpm, 90.0);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::{metrical, midi_file, Event};
+use taiko::{json_structures::custom::Config, MidiConverter};
+
+const PPQ: u16 = 480;
+
+fn convert(ppq: u16, notes: &[(u64, Event)]) -> taiko::json_structures::edda_objects::Root {
+    let bytes = midi_file(
+        metrical(ppq),
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        "Hard",
+        notes,
+    );
+    let (root, _) = MidiConverter::from_bytes(bytes)
+        .with_config(&Config::default())
+        .convert_merged()
+        .unwrap();
+    root
+}
+
+#[test]
+fn grid_notes_in_long_songs_land_on_whole_beats() {
+    // Sixteenth notes for 500 bars.
+    let notes: Vec<(u64, Event)> = (0..8000)
+        .map(|i| (i * PPQ as u64 / 4, Event::Note(60)))
+        .collect();
+    let root = convert(PPQ, &notes);
+
+    assert_eq!(root.notes.len(), 8000);
+    for (i, note) in root.notes.iter().enumerate() {
+        assert_eq!(note.time, i as f64 / 4.0);
+    }
+}
+
+#[test]
+fn grid_notes_serialize_as_clean_beat_values() {
+    let notes: Vec<(u64, Event)> = (0..=64).map(|beat| (beat * 96, Event::Note(61))).collect();
+    let root = convert(96, &notes);
+    let json = serde_json::to_string(&root).unwrap();
+
+    assert!(json.contains("\"_time\":64.0"));
+    assert!(!json.contains("63.99"));
+    assert_eq!(root.custom_data.bpmchanges[0].bpm, 120.0);
+}
+
+#[test]
+fn triplets_are_rounded_to_the_configured_precision() {
+    let notes: Vec<(u64, Event)> = (0..300)
+        .map(|i| (i * PPQ as u64 / 3, Event::Note(62)))
+        .collect();
+    let bytes = midi_file(metrical(PPQ), &[(0, Event::Tempo(500_000))], "Hard", &notes);
+    let config = Config {
+        time_precision: 3,
+        ..Default::default()
+    };
+    let (root, _) = MidiConverter::from_bytes(bytes)
+        .with_config(&config)
+        .convert_merged()
+        .unwrap();
+
+    assert_eq!(root.notes[1].time, 0.333);
+    assert_eq!(root.notes[2].time, 0.667);
+    assert_eq!(root.notes[299].time, 99.667);
+}
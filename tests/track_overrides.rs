@@ -0,0 +1,94 @@
+mod common;
+
+use common::{metrical, midi_file_with_tracks, Event};
+use taiko::{
+    json_structures::custom::{Config, TrackSettings},
+    DatConverter, MidiConverter,
+};
+
+fn two_tracks() -> Vec<u8> {
+    let easy = [(0, Event::Note(60)), (480, Event::Note(61))];
+    let hard = [
+        (0, Event::Note(36)),
+        (480, Event::NoteWithVelocity(38, 20)),
+        (960, Event::NoteWithVelocity(38, 90)),
+        (1440, Event::Note(49)),
+    ];
+    midi_file_with_tracks(
+        metrical(480),
+        &[(0, Event::Tempo(500_000)), (0, Event::TimeSignature(4, 4))],
+        &[("Easy", &easy), ("Hard", &hard)],
+    )
+}
+
+fn hard_settings() -> Config {
+    let json = r#"{"track_overrides": {"hard": {
+        "drum_map": [36, 38, 42, 49], "min_velocity": 40, "time_offset": -0.5
+    }}}"#;
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn converts_tracks_with_their_own_settings() {
+    let config = hard_settings();
+    let conversion = MidiConverter::from_bytes(two_tracks())
+        .with_config(&config)
+        .convert()
+        .unwrap();
+    let notes: Vec<Vec<(f64, i64)>> = conversion
+        .difficulties
+        .iter()
+        .map(|(_, root)| root.notes.iter().map(|n| (n.time, n.line_index)).collect())
+        .collect();
+
+    // Easy keeps the general drum map and timing.
+    assert_eq!(notes[0], vec![(0.0, 0), (1.0, 1)]);
+    // Hard drops the soft note and moves every note half a beat earlier, which leaves out
+    // the note on the first beat.
+    assert_eq!(notes[1], vec![(1.5, 1), (2.5, 3)]);
+    assert_eq!(conversion.report.soft_notes, vec![("Hard".to_string(), 1)]);
+    assert_eq!(conversion.report.early_notes, vec![("Hard".to_string(), 1)]);
+    assert!(conversion.report.unmapped.is_empty());
+}
+
+#[test]
+fn finds_track_settings_ignoring_case() {
+    let config = hard_settings();
+
+    assert_eq!(config.drum_map_for(" HARD ")[0].to_string(), "36");
+    assert_eq!(config.drum_map_for("Easy"), config.drum_map.as_slice());
+    assert_eq!(config.track_settings("Hard").unwrap().min_velocity, 40);
+    assert_eq!(
+        Config::default().track_settings("Hard"),
+        None::<&TrackSettings>
+    );
+}
+
+#[test]
+fn converts_back_with_the_track_settings() {
+    let config = hard_settings();
+    let (_, hard) = MidiConverter::from_bytes(two_tracks())
+        .with_config(&config)
+        .convert()
+        .unwrap()
+        .difficulties
+        .remove(1);
+
+    let midi = DatConverter::from_bytes(serde_json::to_vec(&hard).unwrap())
+        .with_config(&config)
+        .with_track_name("Hard")
+        .convert()
+        .unwrap()
+        .midi;
+    let again = MidiConverter::from_bytes(midi)
+        .with_config(&config)
+        .convert()
+        .unwrap();
+
+    // Converting back undoes the time offset, so the beatmap survives the round trip.
+    let (_, root) = &again.difficulties[0];
+    let notes: Vec<(f64, i64)> = root.notes.iter().map(|n| (n.time, n.line_index)).collect();
+    assert_eq!(notes, vec![(1.5, 1), (2.5, 3)]);
+    assert!(again.report.soft_notes.is_empty());
+    assert!(again.report.early_notes.is_empty());
+}
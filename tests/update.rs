@@ -0,0 +1,181 @@
+use serde_json::json;
+use taiko::{
+    file_io,
+    json_structures::{
+        custom::{BeatRange, SongMetadata},
+        edda_info,
+        edda_objects::Root,
+    },
+};
+
+fn root(notes: &[(f64, i64)], changes: &[(f64, f64)]) -> Root {
+    let notes: Vec<_> = notes
+        .iter()
+        .map(|(time, lane)| {
+            json!({"_time": time, "_lineIndex": lane, "_lineLayer": 0, "_type": 0, "_cutDirection": 1})
+        })
+        .collect();
+    let changes: Vec<_> = changes
+        .iter()
+        .map(|(time, bpm)| {
+            json!({"_BPM": bpm, "_time": time, "_beatsPerBar": 4, "_metronomeOffset": 4})
+        })
+        .collect();
+    serde_json::from_value(json!({
+        "_version": "1",
+        "_customData": {"_time": 0, "_BPMChanges": changes, "_bookmarks": [{"_time": 8.0, "_name": "Chorus"}]},
+        "_events": [{"_time": 2.0, "_type": 0, "_value": 1}],
+        "_notes": notes,
+        "_obstacles": [{"_time": 4.0, "_lineIndex": 0, "_type": 0, "_duration": 1.0, "_width": 1}],
+    }))
+    .unwrap()
+}
+
+fn notes(root: &Root) -> Vec<(f64, i64)> {
+    root.notes.iter().map(|n| (n.time, n.line_index)).collect()
+}
+
+fn changes(root: &Root) -> Vec<(f64, f64)> {
+    root.custom_data
+        .bpmchanges
+        .iter()
+        .map(|c| (c.time, c.bpm))
+        .collect()
+}
+
+#[test]
+fn replaces_notes_and_tempo_but_keeps_edda_edits() {
+    let mut existing = root(&[(0.0, 0), (1.0, 1)], &[(0.0, 120.0)]);
+    let mut converted = root(&[(0.0, 2), (2.0, 3)], &[(0.0, 100.0), (4.0, 90.0)]);
+    converted.events.clear();
+    converted.obstacles.clear();
+    converted.custom_data.bookmarks.clear();
+    let kept = existing.clone();
+
+    existing.update_from(&converted, None);
+
+    assert_eq!(notes(&existing), vec![(0.0, 2), (2.0, 3)]);
+    assert_eq!(changes(&existing), vec![(0.0, 100.0), (4.0, 90.0)]);
+    assert_eq!(existing.events, kept.events);
+    assert_eq!(existing.obstacles, kept.obstacles);
+    assert_eq!(existing.custom_data.bookmarks, kept.custom_data.bookmarks);
+}
+
+#[test]
+fn updates_only_the_given_beats() {
+    let mut existing = root(
+        &[(0.0, 0), (4.0, 0), (6.0, 0), (8.0, 0)],
+        &[(0.0, 120.0), (6.0, 140.0)],
+    );
+    let converted = root(
+        &[(0.0, 3), (4.0, 3), (5.0, 3), (8.0, 3)],
+        &[(0.0, 100.0), (5.0, 110.0)],
+    );
+
+    existing.update_from(&converted, Some("4-8".parse().unwrap()));
+
+    assert_eq!(
+        notes(&existing),
+        vec![(0.0, 0), (4.0, 3), (5.0, 3), (8.0, 0)]
+    );
+    // The converted tempo takes over at beat 4 and the map's own tempo returns at beat 8.
+    assert_eq!(
+        changes(&existing),
+        vec![(0.0, 120.0), (4.0, 100.0), (5.0, 110.0), (8.0, 140.0)]
+    );
+}
+
+#[test]
+fn leaves_out_tempo_changes_that_change_nothing() {
+    let mut existing = root(&[], &[(0.0, 120.0)]);
+    let converted = root(&[(2.0, 1)], &[(0.0, 120.0)]);
+
+    existing.update_from(&converted, Some("1-3".parse().unwrap()));
+
+    assert_eq!(changes(&existing), vec![(0.0, 120.0)]);
+    assert_eq!(notes(&existing), vec![(2.0, 1)]);
+}
+
+#[test]
+fn updates_files_on_disk() {
+    let path = std::env::temp_dir().join(format!("taiko-update-{}.dat", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let converted = root(&[(1.0, 1)], &[(0.0, 100.0)]);
+
+    // A missing file is written as usual.
+    let _ = std::fs::remove_file(&path);
+    file_io::update_output_json(&path, &converted, None).unwrap();
+    let written: Root = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(written, converted);
+
+    let existing = root(&[(0.0, 0)], &[(0.0, 120.0)]);
+    file_io::write_output_json(&path, &existing).unwrap();
+    let mut empty = converted.clone();
+    empty.events.clear();
+    file_io::update_output_json(&path, &empty, None).unwrap();
+    let updated: Root = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(notes(&updated), vec![(1.0, 1)]);
+    assert_eq!(updated.events, existing.events);
+}
+
+#[test]
+fn keeps_song_details_of_an_existing_info_dat() {
+    let path = std::env::temp_dir().join(format!("taiko-info-{}.dat", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let song = SongMetadata {
+        song_name: "song".to_string(),
+        ..Default::default()
+    };
+    let first = [("Hard".to_string(), root(&[(1.0, 1)], &[(0.0, 120.0)]))];
+    let _ = std::fs::remove_file(&path);
+    file_io::update_info_json(
+        &path,
+        &edda_info::Root::from_difficulties(&song, &first, ".dat"),
+    )
+    .unwrap();
+
+    // Edited in Edda, then converted again with another difficulty and tempo.
+    let mut edited: edda_info::Root =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    edited.song_name = "Song Title".to_string();
+    edited.song_author_name = "Band".to_string();
+    edited.song_filename = "song.ogg".to_string();
+    edited.preview_start_time = 30;
+    edited.difficulty_beatmap_sets[0].difficulty_beatmaps[0].note_jump_movement_speed = 18.0;
+    file_io::write_output_json(&path, &edited).unwrap();
+    let second = [
+        ("Easy".to_string(), root(&[(1.0, 1)], &[(0.0, 140.0)])),
+        ("Hard".to_string(), root(&[(1.0, 1)], &[(0.0, 140.0)])),
+    ];
+    file_io::update_info_json(
+        &path,
+        &edda_info::Root::from_difficulties(&song, &second, ".dat"),
+    )
+    .unwrap();
+    let updated: edda_info::Root =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let beatmaps = &updated.difficulty_beatmap_sets[0].difficulty_beatmaps;
+    assert_eq!(updated.song_name, "Song Title");
+    assert_eq!(updated.song_author_name, "Band");
+    assert_eq!(updated.song_filename, "song.ogg");
+    assert_eq!(updated.preview_start_time, 30);
+    assert_eq!(updated.beats_per_minute, 140.0);
+    assert_eq!(beatmaps.len(), 2);
+    assert_eq!(beatmaps[1].beatmap_filename, "Hard.dat");
+    assert_eq!(beatmaps[1].note_jump_movement_speed, 18.0);
+}
+
+#[test]
+fn parses_beat_ranges() {
+    let range: BeatRange = "8.5-12".parse().unwrap();
+
+    assert_eq!((range.start, range.end), (8.5, 12.0));
+    assert!(range.contains(8.5) && !range.contains(12.0));
+    assert!("12-8".parse::<BeatRange>().is_err());
+    assert!("8".parse::<BeatRange>().is_err());
+    assert!("-1-4".parse::<BeatRange>().is_err());
+}
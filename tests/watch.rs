@@ -0,0 +1,63 @@
+use std::{thread, time::Duration};
+
+use taiko::watch::{count_changes, SourceWatcher};
+
+fn counts(entries: &[(&str, usize)]) -> Vec<(String, usize)> {
+    entries
+        .iter()
+        .map(|(name, count)| (name.to_string(), *count))
+        .collect()
+}
+
+#[test]
+fn summarizes_note_count_changes() {
+    let before = counts(&[("Easy", 40), ("Normal", 80), ("Hard", 120)]);
+    let after = counts(&[("Normal", 80), ("Hard", 124), ("Expert", 200), ("Easy", 38)]);
+
+    assert_eq!(
+        count_changes(&before, &after),
+        vec![
+            "Normal: 80 notes (unchanged)",
+            "Hard: 124 notes (+4)",
+            "Expert: 200 notes (new)",
+            "Easy: 38 notes (-2)",
+        ]
+    );
+    assert_eq!(
+        count_changes(&after, &counts(&[("Hard", 124)])),
+        vec![
+            "Hard: 124 notes (unchanged)",
+            "Normal: removed",
+            "Expert: removed",
+            "Easy: removed",
+        ]
+    );
+}
+
+#[test]
+fn reports_a_change_once_the_file_settles() {
+    let path = std::env::temp_dir().join(format!("taiko-watch-{}.mid", std::process::id()));
+    std::fs::write(&path, b"first").unwrap();
+    let debounce = Duration::from_millis(200);
+    let mut watcher = SourceWatcher::new(&path, debounce);
+
+    assert!(!watcher.poll());
+
+    // Two quick saves are one change, reported after the file stops changing.
+    std::fs::write(&path, b"second save").unwrap();
+    assert!(!watcher.poll());
+    thread::sleep(Duration::from_millis(50));
+    std::fs::write(&path, b"third save, longer").unwrap();
+    assert!(!watcher.poll());
+    thread::sleep(debounce + Duration::from_millis(50));
+    let changed = watcher.poll();
+    let again = watcher.poll();
+
+    // A file that is briefly missing while it is replaced is not a change.
+    std::fs::remove_file(&path).unwrap();
+    let missing = watcher.poll();
+
+    assert!(changed);
+    assert!(!again);
+    assert!(!missing);
+}